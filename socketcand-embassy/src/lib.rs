@@ -0,0 +1,34 @@
+//! Async socketcand server for [Embassy](https://embassy.dev) firmware.
+//!
+//! The server runs on an [`embassy_net::tcp::TcpSocket`] and serves one
+//! client at a time. Call [`Server::accept`] to wait for a client, then
+//! [`Server::recv`] until the connection ends.
+//!
+//! ```rust,ignore
+//! let socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+//! let mut server = Server::new(socket, Port::default());
+//!
+//! loop {
+//!     server.accept().await?;
+//!
+//!     while let Ok(cmd) = server.recv().await {
+//!         // handle the command
+//!     }
+//! }
+//! ```
+#![cfg_attr(not(test), no_std)]
+
+mod server;
+
+pub use server::{Error, Server};
+
+/// Socketcand TCP port.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Port(pub u16);
+
+impl Default for Port {
+    fn default() -> Self {
+        Port(29536)
+    }
+}
@@ -0,0 +1,201 @@
+use crate::Port;
+use core::{str::from_utf8, time::Duration};
+use embassy_net::tcp::{AcceptError, State, TcpSocket};
+use embedded_can::Frame;
+use embedded_io_async::Write;
+use heapless::{String, Vec};
+use socketcand::{
+    wire::{command, format_frame, Command, MAX_FD_DATA_LEN, MAX_XL_DATA_LEN},
+    Mode,
+};
+
+/// Longest payload of a command, in bytes.
+const MAX_PAYLOAD_LEN: usize = if MAX_XL_DATA_LEN > MAX_FD_DATA_LEN {
+    MAX_XL_DATA_LEN
+} else {
+    MAX_FD_DATA_LEN
+};
+
+/// Receive buffer length.
+///
+/// Fits the longest command, an `< xlsend >` or `< fdsend >` with a full
+/// payload: three characters per data byte plus the command and its header
+/// fields.
+const RX_BUFFER_LEN: usize = 3 * MAX_PAYLOAD_LEN + 64;
+
+/// Server error.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum Error {
+    /// TCP socket error.
+    Tcp(embassy_net::tcp::Error),
+    /// Failed to accept a connection.
+    Accept(AcceptError),
+    /// The client closed the connection.
+    Disconnected,
+    /// The client sent a message that could not be parsed.
+    Parse,
+    /// The client sent a message longer than the receive buffer.
+    Overflow,
+}
+
+impl From<embassy_net::tcp::Error> for Error {
+    fn from(err: embassy_net::tcp::Error) -> Self {
+        Error::Tcp(err)
+    }
+}
+
+impl From<AcceptError> for Error {
+    fn from(err: AcceptError) -> Self {
+        Error::Accept(err)
+    }
+}
+
+/// State container for a connection.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+struct ConnectionState {
+    /// has the < hi > welcome response been sent
+    welcome: bool,
+    mode: Option<Mode>,
+}
+
+/// Async socketcand server serving one connection at a time on a TCP socket.
+pub struct Server<'a> {
+    socket: TcpSocket<'a>,
+    port: Port,
+    state: ConnectionState,
+    buffer: Vec<u8, RX_BUFFER_LEN>,
+}
+
+impl<'a> Server<'a> {
+    /// Creates a new socketcand server on a TCP socket.
+    pub fn new(socket: TcpSocket<'a>, port: Port) -> Self {
+        Self {
+            socket,
+            port,
+            state: ConnectionState::default(),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Consumes the server, returning the underlying socket.
+    pub fn into_inner(self) -> TcpSocket<'a> {
+        self.socket
+    }
+
+    /// Wait for a client to connect.
+    ///
+    /// Any previous connection is aborted and its state discarded.
+    pub async fn accept(&mut self) -> Result<(), Error> {
+        if self.socket.state() != State::Closed {
+            self.socket.abort();
+            // the connection is being discarded, a reset error is expected
+            self.socket.flush().await.ok();
+        }
+
+        self.state = ConnectionState::default();
+        self.buffer.clear();
+
+        self.socket.accept(self.port.0).await?;
+
+        self.welcome().await
+    }
+
+    /// Send the < hi > welcome message if it has not been sent yet.
+    async fn welcome(&mut self) -> Result<(), Error> {
+        if !self.state.welcome {
+            self.socket.write_all("< hi >".as_bytes()).await?;
+            self.state.welcome = true;
+        }
+
+        Ok(())
+    }
+
+    /// Receive the next command from the client.
+    pub async fn recv(&mut self) -> Result<Command, Error> {
+        self.welcome().await?;
+
+        loop {
+            if let Some(end) = self.buffer.iter().position(|&b| b == b'>') {
+                let parsed = match from_utf8(&self.buffer[..=end]) {
+                    Ok(ascii) => command(ascii).map(|(_, cmd)| cmd).ok(),
+                    Err(_) => None,
+                };
+
+                // drop the message from the receive buffer
+                self.buffer.rotate_left(end + 1);
+                self.buffer.truncate(self.buffer.len() - (end + 1));
+
+                let Some(cmd) = parsed else {
+                    return Err(Error::Parse);
+                };
+
+                self.handle(&cmd).await?;
+
+                return Ok(cmd);
+            }
+
+            if self.buffer.is_full() {
+                self.buffer.clear();
+                return Err(Error::Overflow);
+            }
+
+            let len = self.buffer.len();
+            self.buffer.resize_default(RX_BUFFER_LEN).ok();
+            let read = self.socket.read(&mut self.buffer[len..]).await;
+            let read = match read {
+                Ok(read) => read,
+                Err(err) => {
+                    self.buffer.truncate(len);
+                    return Err(Error::Tcp(err));
+                }
+            };
+            self.buffer.truncate(len + read);
+
+            if read == 0 {
+                self.socket.close();
+                return Err(Error::Disconnected);
+            }
+        }
+    }
+
+    /// Update connection state from a received command.
+    async fn handle(&mut self, cmd: &Command) -> Result<(), Error> {
+        let mode = match cmd {
+            Command::Open(open) => open.mode.clone().unwrap_or(Mode::Broadcast),
+            Command::RawMode(_) => Mode::Raw,
+            Command::BroadcastMode(_) => Mode::Broadcast,
+            Command::ControlMode(_) => Mode::Control,
+            Command::IsoTpMode(_) => Mode::IsoTp,
+            _ => return Ok(()),
+        };
+
+        self.state.mode = Some(mode);
+        self.socket.write_all("< ok >".as_bytes()).await?;
+
+        Ok(())
+    }
+
+    /// Send a CAN frame.
+    ///
+    /// Frames are only forwarded to the client in raw mode.
+    pub async fn send_frame(
+        &mut self,
+        timestamp: Duration,
+        frame: &impl Frame,
+    ) -> Result<(), Error> {
+        self.welcome().await?;
+
+        if self.state.mode == Some(Mode::Raw) {
+            let mut out = String::<128>::new();
+
+            format_frame(&mut out, frame.id(), timestamp, frame.data())
+                .unwrap();
+
+            self.socket.write_all(out.as_bytes()).await?;
+        }
+
+        Ok(())
+    }
+}
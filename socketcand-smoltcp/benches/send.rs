@@ -0,0 +1,195 @@
+//! Compare `Server::send_batch` with repeated calls to `Server::send_frame`.
+//!
+//! Each iteration forwards a burst of frames to a client connected over a
+//! loopback interface, then polls the interface and drains the client
+//! socket, so the TCP segments produced are included in the measurement.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use embedded_can::{Frame as _, StandardId};
+use smoltcp::{
+    iface::{Config, Interface, SocketHandle, SocketSet, SocketStorage},
+    phy::{self, Device, DeviceCapabilities, Medium},
+    socket::tcp::{Socket, SocketBuffer},
+    time::{Duration, Instant},
+    wire::{HardwareAddress, IpAddress, IpCidr},
+};
+use socketcand::wire::Frame;
+use socketcand_smoltcp::{Port, Server};
+use std::collections::VecDeque;
+
+const LOCALHOST: IpAddress = IpAddress::v4(127, 0, 0, 1);
+
+/// Frames forwarded per iteration.
+const BURST: usize = 16;
+
+/// Loopback device, like `smoltcp::phy::Loopback` but without needing
+/// smoltcp's `alloc` feature.
+#[derive(Default)]
+struct Loopback {
+    queue: VecDeque<Vec<u8>>,
+}
+
+struct RxToken(Vec<u8>);
+
+struct TxToken<'a>(&'a mut VecDeque<Vec<u8>>);
+
+impl phy::RxToken for RxToken {
+    fn consume<R, F>(mut self, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        f(&mut self.0)
+    }
+}
+
+impl phy::TxToken for TxToken<'_> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut packet = vec![0; len];
+        let result = f(&mut packet);
+        self.0.push_back(packet);
+        result
+    }
+}
+
+impl Device for Loopback {
+    type RxToken<'a> = RxToken;
+    type TxToken<'a> = TxToken<'a>;
+
+    fn receive(
+        &mut self,
+        _timestamp: Instant,
+    ) -> Option<(RxToken, TxToken<'_>)> {
+        let packet = self.queue.pop_front()?;
+        Some((RxToken(packet), TxToken(&mut self.queue)))
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<TxToken<'_>> {
+        Some(TxToken(&mut self.queue))
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut caps = DeviceCapabilities::default();
+        caps.medium = Medium::Ip;
+        caps.max_transmission_unit = 1500;
+        caps
+    }
+}
+
+/// A server and a client in raw mode connected over a loopback interface.
+struct Setup {
+    device: Loopback,
+    iface: Interface,
+    sockets: SocketSet<'static>,
+    server: Server<'static>,
+    client: SocketHandle,
+    now: Instant,
+}
+
+fn tcp_socket() -> Socket<'static> {
+    let buffer = || SocketBuffer::new(Box::leak(vec![0; 4096].into()));
+    Socket::new(buffer(), buffer())
+}
+
+impl Setup {
+    fn new() -> Self {
+        let now = Instant::ZERO;
+        let mut device = Loopback::default();
+        let mut iface =
+            Interface::new(Config::new(HardwareAddress::Ip), &mut device, now);
+        iface.update_ip_addrs(|addrs| {
+            addrs.push(IpCidr::new(LOCALHOST, 8)).unwrap();
+        });
+
+        let storage = Box::leak(Box::new([SocketStorage::EMPTY; 2]));
+        let mut sockets = SocketSet::new(&mut storage[..]);
+        let port = Port::default();
+        let server = Server::new(&mut sockets, tcp_socket(), port);
+        let client = sockets.add(tcp_socket());
+
+        let mut setup = Self {
+            device,
+            iface,
+            sockets,
+            server,
+            client,
+            now,
+        };
+
+        setup.server.recv_at(&mut setup.sockets, setup.now).unwrap();
+        let socket = setup.sockets.get_mut::<Socket>(setup.client);
+        socket
+            .connect(setup.iface.context(), (LOCALHOST, port.0), 49152)
+            .unwrap();
+        setup.poll();
+        setup.server.recv_at(&mut setup.sockets, setup.now).unwrap();
+        setup.poll();
+
+        let socket = setup.sockets.get_mut::<Socket>(setup.client);
+        socket.send_slice(b"< rawmode >").unwrap();
+        setup.poll();
+        setup.server.recv_at(&mut setup.sockets, setup.now).unwrap();
+        setup.drain();
+
+        setup
+    }
+
+    /// Advance time and exchange packets until the interface is idle.
+    fn poll(&mut self) {
+        for _ in 0..8 {
+            self.now += Duration::from_millis(1);
+            self.iface
+                .poll(self.now, &mut self.device, &mut self.sockets);
+        }
+    }
+
+    /// Deliver everything the server sent and discard it on the client.
+    fn drain(&mut self) {
+        self.poll();
+
+        let socket = self.sockets.get_mut::<Socket>(self.client);
+        while socket.can_recv() {
+            socket.recv(|data| (data.len(), ())).unwrap();
+        }
+    }
+}
+
+fn send(c: &mut Criterion) {
+    let id = StandardId::new(0x123).unwrap();
+    let frame = Frame::new(id, &[0x11; 8]).unwrap();
+    let frames = [&frame; BURST];
+
+    let mut group = c.benchmark_group("send");
+
+    let mut setup = Setup::new();
+    group.bench_function("send_frame", |b| {
+        b.iter(|| {
+            for frame in frames {
+                setup
+                    .server
+                    .send_frame(&mut setup.sockets, setup.now, frame)
+                    .unwrap();
+            }
+            setup.drain();
+        })
+    });
+
+    let mut setup = Setup::new();
+    group.bench_function("send_batch", |b| {
+        b.iter(|| {
+            let sent = setup
+                .server
+                .send_batch(&mut setup.sockets, setup.now, &frames)
+                .unwrap();
+            assert_eq!(sent, BURST);
+            setup.drain();
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, send);
+criterion_main!(benches);
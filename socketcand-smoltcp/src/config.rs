@@ -0,0 +1,213 @@
+use crate::Port;
+use heapless::{String, Vec};
+use smoltcp::{iface::SocketHandle, time::Duration};
+use socketcand::{wire::MAX_VERSION_LEN, Bus};
+
+/// Maximum authentication token length.
+pub const MAX_AUTH_TOKEN_LEN: usize = 64;
+
+/// Maximum number of busses a server can advertise.
+pub const MAX_BUSES: usize = 16;
+
+/// Maximum number of busses with their own socket.
+pub const MAX_BUS_SOCKETS: usize = 8;
+
+/// Server configuration error.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum ConfigError {
+    /// The authentication token is longer than [`MAX_AUTH_TOKEN_LEN`] bytes.
+    AuthTokenTooLong,
+    /// More than [`MAX_BUSES`] busses were registered.
+    TooManyBuses,
+    /// More than [`MAX_BUS_SOCKETS`] bus sockets were added.
+    TooManyBusSockets,
+    /// The version string is longer than [`MAX_VERSION_LEN`] bytes or
+    /// contains spaces.
+    InvalidVersion,
+}
+
+/// Socketcand server configuration.
+///
+/// # Example
+/// ```rust
+/// use socketcand_smoltcp::{Port, ServerConfig};
+///
+/// let config = ServerConfig::new(Port::default())
+///     .with_auth_token("s3cret")
+///     .unwrap();
+/// ```
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct ServerConfig {
+    pub(crate) port: Port,
+    pub(crate) auth_token: Option<String<MAX_AUTH_TOKEN_LEN>>,
+    pub(crate) version: Option<&'static str>,
+    /// listen attempts and the delay between them in milliseconds
+    pub(crate) listen_retry: Option<(u8, u64)>,
+    /// TCP keep-alive interval
+    pub(crate) tcp_keepalive: Option<Duration>,
+    /// default statistics interval, copied into each new connection
+    pub(crate) stats_interval: Option<core::time::Duration>,
+    /// busses advertised in response to `< get_busses >`
+    pub(crate) buses: Vec<Bus, MAX_BUSES>,
+    /// sockets used to reach each bus
+    pub(crate) bus_sockets: Vec<(Bus, SocketHandle), MAX_BUS_SOCKETS>,
+}
+
+impl ServerConfig {
+    /// Create a new [`ServerConfig`] listening on the given port.
+    pub fn new(port: Port) -> Self {
+        Self {
+            port,
+            auth_token: None,
+            version: None,
+            listen_retry: None,
+            tcp_keepalive: None,
+            stats_interval: None,
+            buses: Vec::new(),
+            bus_sockets: Vec::new(),
+        }
+    }
+
+    /// Require clients to authenticate with `< auth TOKEN >` before any other
+    /// command is processed.
+    ///
+    /// Returns an error if the token is longer than [`MAX_AUTH_TOKEN_LEN`]
+    /// bytes.
+    pub fn with_auth_token(mut self, token: &str) -> Result<Self, ConfigError> {
+        let token = String::try_from(token)
+            .map_err(|_| ConfigError::AuthTokenTooLong)?;
+        self.auth_token = Some(token);
+        Ok(self)
+    }
+
+    /// Advertise `buses` in response to `< get_busses >`.
+    ///
+    /// Once busses are registered, clients may only open those. Returns an
+    /// error if more than [`MAX_BUSES`] busses are registered in total.
+    pub fn with_buses(mut self, buses: &[Bus]) -> Result<Self, ConfigError> {
+        self.buses
+            .extend_from_slice(buses)
+            .map_err(|_| ConfigError::TooManyBuses)?;
+        Ok(self)
+    }
+
+    /// Route frames for `bus` through `socket`, e.g. to send them from the
+    /// network interface and source address the bus is attached to.
+    ///
+    /// The socket for the bus the client opens is returned by
+    /// [`Server::bus_socket`](crate::Server::bus_socket). Returns an error if
+    /// more than [`MAX_BUS_SOCKETS`] busses are given a socket.
+    pub fn with_bus_socket(
+        mut self,
+        bus: Bus,
+        socket: SocketHandle,
+    ) -> Result<Self, ConfigError> {
+        match self.bus_sockets.iter_mut().find(|(b, _)| *b == bus) {
+            Some(entry) => entry.1 = socket,
+            None => self
+                .bus_sockets
+                .push((bus, socket))
+                .map_err(|_| ConfigError::TooManyBusSockets)?,
+        }
+        Ok(self)
+    }
+
+    /// Reply to `< get_version >` with `< version VERSION >`, and to
+    /// `< echo >` with `< echo version=VERSION >`.
+    ///
+    /// Without this the command is rejected with
+    /// `< nack reason="unsupported" >`.
+    pub fn with_version_string(
+        mut self,
+        version: &'static str,
+    ) -> Result<Self, ConfigError> {
+        if version.len() > MAX_VERSION_LEN || version.contains(' ') {
+            return Err(ConfigError::InvalidVersion);
+        }
+        self.version = Some(version);
+        Ok(self)
+    }
+
+    /// Try to listen up to `attempts` times, waiting `delay_ms` milliseconds
+    /// between attempts, e.g. while the port is still in use.
+    ///
+    /// The server panics once all attempts have failed. Without this, failures
+    /// are logged and listening is retried on every call. The delay is
+    /// measured from the `now` passed to server methods such as
+    /// [`Server::recv_at`](crate::Server::recv_at); methods without it do not
+    /// retry.
+    pub fn retry_listen(mut self, attempts: u8, delay_ms: u64) -> Self {
+        self.listen_retry = Some((attempts, delay_ms));
+        self
+    }
+
+    /// Send TCP keep-alive packets after `interval` without traffic.
+    ///
+    /// A client that stops responding is detected, rather than holding the
+    /// server indefinitely on an unreliable network.
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.tcp_keepalive = Some(interval);
+        self
+    }
+
+    /// Report statistics every `interval` by default, until the client sends
+    /// its own `< statistics >` command.
+    ///
+    /// Statistics are sent by
+    /// [`Server::poll_statistics`](crate::Server::poll_statistics).
+    pub fn with_statistics_interval(
+        mut self,
+        interval: core::time::Duration,
+    ) -> Self {
+        self.stats_interval = Some(interval);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_token_too_long() {
+        let token = "a".repeat(MAX_AUTH_TOKEN_LEN + 1);
+
+        assert_eq!(
+            ServerConfig::default().with_auth_token(&token).err(),
+            Some(ConfigError::AuthTokenTooLong)
+        );
+        assert!(ServerConfig::default().with_auth_token(&token[1..]).is_ok());
+    }
+
+    #[test]
+    fn too_many_buses() {
+        let buses = [Bus::new(0); MAX_BUSES + 1];
+
+        assert_eq!(
+            ServerConfig::default().with_buses(&buses).err(),
+            Some(ConfigError::TooManyBuses)
+        );
+        assert!(ServerConfig::default()
+            .with_buses(&buses[1..])
+            .unwrap()
+            .with_buses(&buses[1..])
+            .is_err());
+    }
+
+    #[test]
+    fn invalid_version() {
+        assert_eq!(
+            ServerConfig::default().with_version_string("1.2 0").err(),
+            Some(ConfigError::InvalidVersion)
+        );
+        assert_eq!(
+            ServerConfig::default()
+                .with_version_string("0123456789abcdef0123456789abcdef0")
+                .err(),
+            Some(ConfigError::InvalidVersion)
+        );
+        assert!(ServerConfig::default().with_version_string("1.2.0").is_ok());
+    }
+}
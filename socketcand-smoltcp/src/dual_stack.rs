@@ -0,0 +1,87 @@
+use crate::{Server, ServerConfig};
+use embedded_can::Frame;
+use smoltcp::{
+    iface::SocketSet,
+    socket::tcp::{RecvError, SendError, Socket},
+    time::Instant,
+    wire::{IpAddress, IpVersion, Ipv4Address, Ipv6Address},
+};
+use socketcand::wire::Command;
+
+/// Socketcand server listening on both an IPv4 and an IPv6 address.
+///
+/// Each address has its own socket and connection state; commands and
+/// frames are proxied through the same API as [`Server`].
+#[derive(Debug)]
+pub struct DualStackServer<'cb> {
+    v4: Server<'cb>,
+    v6: Server<'cb>,
+}
+
+impl<'cb> DualStackServer<'cb> {
+    /// Creates a new dual-stack socketcand server.
+    pub fn new<'a>(
+        sockets: &mut SocketSet<'a>,
+        v4_socket: Socket<'a>,
+        v4_addr: Ipv4Address,
+        v6_socket: Socket<'a>,
+        v6_addr: Ipv6Address,
+        config: ServerConfig,
+    ) -> Self {
+        Self {
+            v4: Server::new_with_config(sockets, v4_socket, config.clone())
+                .with_listen_addr(IpAddress::Ipv4(v4_addr)),
+            v6: Server::new_with_config(sockets, v6_socket, config)
+                .with_listen_addr(IpAddress::Ipv6(v6_addr)),
+        }
+    }
+
+    /// Returns the IP version the active client is connected over.
+    pub fn active_stack(&self) -> Option<IpVersion> {
+        self.v4.client_stack().or(self.v6.client_stack())
+    }
+
+    /// Receive a command from either stack if there is any.
+    pub fn recv(
+        &mut self,
+        sockets: &mut SocketSet,
+    ) -> Result<Option<Command>, RecvError> {
+        match self.v4.recv(sockets)? {
+            Some(cmd) => Ok(Some(cmd)),
+            None => self.v6.recv(sockets),
+        }
+    }
+
+    /// Receive a command from either stack if there is any, at time `now`.
+    pub fn recv_at(
+        &mut self,
+        sockets: &mut SocketSet,
+        now: Instant,
+    ) -> Result<Option<Command>, RecvError> {
+        match self.v4.recv_at(sockets, now)? {
+            Some(cmd) => Ok(Some(cmd)),
+            None => self.v6.recv_at(sockets, now),
+        }
+    }
+
+    /// Send a CAN frame to the clients of both stacks.
+    pub fn send_frame(
+        &mut self,
+        sockets: &mut SocketSet,
+        now: Instant,
+        frame: &impl Frame,
+    ) -> Result<(), SendError> {
+        let v4 = self.v4.send_frame(sockets, now, frame);
+        let v6 = self.v6.send_frame(sockets, now, frame);
+
+        v4.and(v6)
+    }
+
+    /// Returns the server for the given IP version.
+    pub fn server(&mut self, version: IpVersion) -> &mut Server<'cb> {
+        match version {
+            IpVersion::Ipv4 => &mut self.v4,
+            IpVersion::Ipv6 => &mut self.v6,
+        }
+    }
+}
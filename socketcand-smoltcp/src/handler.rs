@@ -0,0 +1,163 @@
+//! Callback based command dispatch.
+
+use socketcand::wire::{
+    Add, AddCount, Announce, Auth, BcmRxSetup, BcmTxSetup, BroadcastMode,
+    Command, ControlMode, Delete, DeleteRange, Echo, FdSend, Filter,
+    GetBitrate, GetBusses, GetState, GetVersion, HeartbeatConfig, IsoTpConf,
+    IsoTpMode, LastError, ListJobs, Open, RawMode, RequestFrames, Send,
+    SetBaud, Sleep, Statistics, SubscribeTx, Update, Wakeup, XlSend,
+};
+
+/// Handles commands received by [`Server::handle`](crate::Server::handle).
+///
+/// Every method does nothing by default, so only the commands of interest
+/// need to be implemented. Replies such as `< ok >` are sent by the server
+/// before the handler is called.
+pub trait CommandHandler {
+    /// Called on `< open >`: a bus was opened.
+    fn on_open(&mut self, _open: &Open) {}
+
+    /// Called on `< add >`: a cyclic job was added.
+    fn on_add(&mut self, _add: &Add) {}
+
+    /// Called on `< update >`: a cyclic job was updated.
+    fn on_update(&mut self, _update: &Update) {}
+
+    /// Called on `< delete >`: a cyclic job was deleted.
+    fn on_delete(&mut self, _delete: &Delete) {}
+
+    /// Called on `< send >`: a frame should be transmitted.
+    fn on_send(&mut self, _send: &Send) {}
+
+    /// Called on `< filter >`: a content filter was set up.
+    fn on_filter(&mut self, _filter: &Filter) {}
+
+    /// Called on `< echo >`.
+    fn on_echo(&mut self, _echo: &Echo) {}
+
+    /// Called on `< rawmode >`: the client entered raw mode.
+    fn on_raw_mode(&mut self, _raw_mode: &RawMode) {}
+
+    /// Called on `< bcmode >`: the client entered broadcast mode.
+    fn on_broadcast_mode(&mut self, _broadcast_mode: &BroadcastMode) {}
+
+    /// Called on `< controlmode >`: the client entered control mode.
+    fn on_control_mode(&mut self, _control_mode: &ControlMode) {}
+
+    /// Called on `< isotpmode >`: the client entered ISO-TP mode.
+    fn on_iso_tp_mode(&mut self, _iso_tp_mode: &IsoTpMode) {}
+
+    /// Called on `< statistics >`: the statistics interval changed.
+    fn on_statistics(&mut self, _statistics: &Statistics) {}
+
+    /// Called on `< auth >`.
+    fn on_auth(&mut self, _auth: &Auth) {}
+
+    /// Called on `< list_jobs >`.
+    fn on_list_jobs(&mut self, _list_jobs: &ListJobs) {}
+
+    /// Called on `< get_busses >`.
+    fn on_get_busses(&mut self, _get_busses: &GetBusses) {}
+
+    /// Called on `< tx_setup >`: a transmit job was set up.
+    fn on_bcm_tx_setup(&mut self, _bcm_tx_setup: &BcmTxSetup) {}
+
+    /// Called on `< rx_setup >`: a receive filter was set up.
+    fn on_bcm_rx_setup(&mut self, _bcm_rx_setup: &BcmRxSetup) {}
+
+    /// Called on `< isotpconf >`: ISO-TP was configured.
+    fn on_iso_tp_conf(&mut self, _iso_tp_conf: &IsoTpConf) {}
+
+    /// Called on `< delete_range >`: cyclic jobs were deleted.
+    fn on_delete_range(&mut self, _delete_range: &DeleteRange) {}
+
+    /// Called on `< add_count >`: a job with a limited count was added.
+    fn on_add_count(&mut self, _add_count: &AddCount) {}
+
+    /// Called on `< setbaud >`: the bit rate should change.
+    fn on_set_baud(&mut self, _set_baud: &SetBaud) {}
+
+    /// Called on `< xlsend >`: a CAN XL frame should be transmitted.
+    fn on_xl_send(&mut self, _xl_send: &XlSend) {}
+
+    /// Called on `< get_state >`.
+    fn on_get_state(&mut self, _get_state: &GetState) {}
+
+    /// Called on `< sleep >`: the bus was put to sleep.
+    fn on_sleep(&mut self, _sleep: &Sleep) {}
+
+    /// Called on `< wakeup >`: the bus was woken up.
+    fn on_wakeup(&mut self, _wakeup: &Wakeup) {}
+
+    /// Called on `< get_version >`.
+    fn on_get_version(&mut self, _get_version: &GetVersion) {}
+
+    /// Called on `< fdsend >`: a CAN FD frame should be transmitted.
+    fn on_fd_send(&mut self, _fd_send: &FdSend) {}
+
+    /// Called on `< subscribe_tx >`: the client subscribed to echoes of
+    /// transmitted frames.
+    fn on_subscribe_tx(&mut self, _subscribe_tx: &SubscribeTx) {}
+
+    /// Called on `< heartbeat >`: the heartbeat interval changed.
+    fn on_heartbeat_config(&mut self, _heartbeat_config: &HeartbeatConfig) {}
+
+    /// Called on `< getbitrate >`.
+    fn on_get_bitrate(&mut self, _get_bitrate: &GetBitrate) {}
+
+    /// Called on `< lasterror >`.
+    fn on_last_error(&mut self, _last_error: &LastError) {}
+
+    /// Called on `< announce >`.
+    fn on_announce(&mut self, _announce: &Announce) {}
+
+    /// Called on `< request_frames >`.
+    fn on_request_frames(&mut self, _request_frames: &RequestFrames) {}
+}
+
+/// [`CommandHandler`] that ignores every command.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NullCommandHandler;
+
+impl CommandHandler for NullCommandHandler {}
+
+/// Call the handler method for `cmd`.
+pub(crate) fn dispatch(handler: &mut impl CommandHandler, cmd: &Command) {
+    match cmd {
+        Command::Open(cmd) => handler.on_open(cmd),
+        Command::Add(cmd) => handler.on_add(cmd),
+        Command::Update(cmd) => handler.on_update(cmd),
+        Command::Delete(cmd) => handler.on_delete(cmd),
+        Command::Send(cmd) => handler.on_send(cmd),
+        Command::Filter(cmd) => handler.on_filter(cmd),
+        Command::Echo(cmd) => handler.on_echo(cmd),
+        Command::RawMode(cmd) => handler.on_raw_mode(cmd),
+        Command::BroadcastMode(cmd) => handler.on_broadcast_mode(cmd),
+        Command::ControlMode(cmd) => handler.on_control_mode(cmd),
+        Command::IsoTpMode(cmd) => handler.on_iso_tp_mode(cmd),
+        Command::Statistics(cmd) => handler.on_statistics(cmd),
+        Command::Auth(cmd) => handler.on_auth(cmd),
+        Command::ListJobs(cmd) => handler.on_list_jobs(cmd),
+        Command::GetBusses(cmd) => handler.on_get_busses(cmd),
+        Command::BcmTxSetup(cmd) => handler.on_bcm_tx_setup(cmd),
+        Command::BcmRxSetup(cmd) => handler.on_bcm_rx_setup(cmd),
+        Command::IsoTpConf(cmd) => handler.on_iso_tp_conf(cmd),
+        Command::DeleteRange(cmd) => handler.on_delete_range(cmd),
+        Command::AddCount(cmd) => handler.on_add_count(cmd),
+        Command::SetBaud(cmd) => handler.on_set_baud(cmd),
+        Command::XlSend(cmd) => handler.on_xl_send(cmd),
+        Command::GetState(cmd) => handler.on_get_state(cmd),
+        Command::Sleep(cmd) => handler.on_sleep(cmd),
+        Command::Wakeup(cmd) => handler.on_wakeup(cmd),
+        Command::GetVersion(cmd) => handler.on_get_version(cmd),
+        Command::FdSend(cmd) => handler.on_fd_send(cmd),
+        Command::SubscribeTx(cmd) => handler.on_subscribe_tx(cmd),
+        Command::HeartbeatConfig(cmd) => handler.on_heartbeat_config(cmd),
+        Command::GetBitrate(cmd) => handler.on_get_bitrate(cmd),
+        Command::LastError(cmd) => handler.on_last_error(cmd),
+        Command::Announce(cmd) => handler.on_announce(cmd),
+        Command::RequestFrames(cmd) => handler.on_request_frames(cmd),
+        // keep-alives are consumed by the server
+        Command::Nop(_) => {}
+    }
+}
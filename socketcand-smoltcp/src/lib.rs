@@ -1,11 +1,23 @@
 #![cfg_attr(not(test), no_std)]
 
+mod config;
+#[cfg(feature = "dual-stack")]
+mod dual_stack;
+mod handler;
+mod metrics;
 mod server;
 
-pub use server::Server;
+pub use config::{
+    ConfigError, ServerConfig, MAX_AUTH_TOKEN_LEN, MAX_BUSES, MAX_BUS_SOCKETS,
+};
+#[cfg(feature = "dual-stack")]
+pub use dual_stack::DualStackServer;
+pub use handler::{CommandHandler, NullCommandHandler};
+pub use metrics::ServerMetrics;
+pub use server::{BuslistError, Server, MAX_CONNECTIONS};
 
 /// Socketcand TCP port.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 pub struct Port(pub u16);
 
@@ -0,0 +1,17 @@
+/// Connection throughput counters.
+///
+/// Counters are reset when the client disconnects.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct ServerMetrics {
+    /// Commands parsed successfully.
+    pub commands_received: u64,
+    /// CAN frames forwarded to the client.
+    pub frames_sent: u64,
+    /// CAN frames that could not be forwarded to the client.
+    pub frames_dropped: u64,
+    /// Bytes queued for sending to the client.
+    pub bytes_sent: u64,
+    /// Bytes consumed from the client.
+    pub bytes_received: u64,
+}
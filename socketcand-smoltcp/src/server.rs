@@ -1,83 +1,718 @@
-use crate::Port;
-use core::{fmt::Write, str::from_utf8, task::Waker};
-use embedded_can::Frame;
-use heapless::String;
+use crate::{
+    handler::{dispatch, CommandHandler},
+    Port, ServerConfig, ServerMetrics, MAX_BUSES,
+};
+use core::{
+    fmt::Write, net::IpAddr, str::from_utf8, task::Waker, time::Duration,
+};
+use embedded_can::{Frame, Id};
+use heapless::{FnvIndexMap, String, Vec};
 use smoltcp::{
     iface::{SocketHandle, SocketSet},
     socket::tcp::{RecvError, SendError, Socket, State},
-    time::Instant,
+    time::{self, Instant},
+    wire::{IpAddress, IpListenEndpoint, IpVersion},
 };
 use socketcand::{
-    wire::{command, Command},
-    Mode,
+    beacon::BeaconMessage,
+    bus::MAX_BUS_NAME_LEN,
+    wire::{
+        ascii, command, format_frame, util::normalize_whitespace, Add,
+        Announce, BcmJobTable, BcmRxSetup, Bitrate, BusState, BusStateKind,
+        BusStats, CanId, Command, Echo, EndList, FrameError, IsoTpConf, Job,
+        LastErrorCode, TxEcho, MAX_DATA_LEN,
+    },
+    Bus, Mode,
 };
 
+/// Number of clients a [`Server`] serves at once.
+///
+/// A smoltcp TCP socket has no listen backlog, it handles exactly one
+/// connection. Create a server per client to serve several at once.
+pub const MAX_CONNECTIONS: usize = 1;
+
+/// Maximum ISO-TP message length.
+const MAX_ISOTP_LEN: usize = 4095;
+
+/// Maximum number of cyclic jobs per connection.
+const MAX_JOBS: usize = 32;
+
+/// Maximum number of receive filters per connection.
+const MAX_RX_FILTERS: usize = 16;
+
+/// Maximum number of identifiers a client can subscribe to with
+/// `< subscribe_tx >`.
+const MAX_TX_SUBSCRIPTIONS: usize = 16;
+
+/// Maximum number of identifiers with a cached frame for
+/// `< request_frames >`.
+const MAX_CACHED_FRAMES: usize = 32;
+
+/// Maximum length of the messages sent at once by [`Server::send_batch`].
+const MAX_BATCH_LEN: usize = 1024;
+
 /// State container for a connection.
 ///
 /// This is reset to its default value when the client disconnects, ready for
 /// the next connection.
-#[derive(Debug)]
-#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[derive(Debug, Default, Clone)]
 pub struct ConnectionState {
     /// has the < hi > welcome response been sent
     welcome: bool,
-    mode: Option<Mode>,
+    /// has the client sent a valid < auth > token
+    authenticated: bool,
+    mode: Mode,
+    /// echo frames sent by the client back to it in raw mode
+    loopback: bool,
+    /// active cyclic jobs
+    jobs: BcmJobTable<MAX_JOBS>,
+    /// remaining transmissions for jobs added with `< add_count >`
+    job_counts: FnvIndexMap<CanId, u32, MAX_JOBS>,
+    /// remaining transmissions before broadcasting stops, set with
+    /// `< bcmode COUNT >`
+    broadcast_remaining: Option<u32>,
+    /// receive filters set up with `< rx_setup >`
+    rx_filters: Vec<BcmRxSetup, MAX_RX_FILTERS>,
+    /// statistics reporting interval, if enabled
+    stats_interval: Option<Duration>,
+    /// when the next statistics message is due
+    next_stats_at: Option<Instant>,
+    /// statistics were requested once with `< statistics 0 >`
+    stats_requested: bool,
+    /// when the last command was received
+    last_activity: Option<Instant>,
+    metrics: ServerMetrics,
+    /// IP version the client is connected over
+    stack: Option<IpVersion>,
+    /// bus selected with `< open >`
+    opened_bus: Option<Bus>,
+    /// socket for the bus selected with `< open >`
+    bus_socket: Option<SocketHandle>,
+    /// bus was put to sleep with `< sleep >`
+    sleeping: bool,
+    /// ISO-TP addressing set up with `< isotpconf >`
+    iso_tp_conf: Option<IsoTpConf>,
+    /// identifiers of transmitted frames to echo, set with
+    /// `< subscribe_tx >`
+    tx_subscriptions: Vec<Id, MAX_TX_SUBSCRIPTIONS>,
+}
+
+#[cfg(feature = "defmt-03")]
+impl defmt::Format for ConnectionState {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "ConnectionState {{ welcome: {}, authenticated: {}, mode: {=str}, jobs: {} }}",
+            self.welcome,
+            self.authenticated,
+            self.mode.as_str(),
+            self.jobs.len(),
+        )
+    }
+}
+
+/// Compare `a` and `b` in time that depends only on their lengths, so a
+/// client cannot guess the authentication token byte by byte.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let diff = a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y));
+
+    core::hint::black_box(diff) == 0
+}
+
+/// Send data to the client, recording the number of bytes queued.
+fn send(
+    socket: &mut Socket,
+    metrics: &mut ServerMetrics,
+    data: &[u8],
+) -> Result<usize, SendError> {
+    let sent = socket.send_slice(data)?;
+    metrics.bytes_sent += sent as u64;
+    Ok(sent)
+}
+
+/// Parse the first command in `input` after collapsing runs of whitespace,
+/// for clients that pad their fields, e.g. `< send  123   2  AB CD >`.
+///
+/// Returns the number of bytes of `input` the command took up.
+fn command_padded(input: &str) -> Option<(usize, Command)> {
+    let end = input.find('>')? + 1;
+    let mut buf = String::new();
+    let normalized = normalize_whitespace(input.get(..end)?, &mut buf).ok()?;
+
+    match command(normalized) {
+        Ok(("", cmd)) => Some((end, cmd)),
+        _ => None,
+    }
+}
+
+/// Send an `< echo >` reply.
+fn write_echo(socket: &mut Socket, metrics: &mut ServerMetrics, echo: &Echo) {
+    let mut out = String::<64>::new();
+    write!(&mut out, "{}", echo).unwrap();
+    send(socket, metrics, out.as_bytes()).ok();
+}
+
+/// Reject a command with a `< nack reason="REASON" >` message.
+fn nack(
+    socket: &mut Socket,
+    metrics: &mut ServerMetrics,
+    reason: &str,
+) -> Result<usize, SendError> {
+    let mut out = String::<48>::new();
+    write!(&mut out, "< nack reason=\"{}\" >", reason).unwrap();
+    send(socket, metrics, out.as_bytes())
+}
+
+/// Send a `< stat >` message.
+fn write_stats(
+    socket: &mut Socket,
+    metrics: &mut ServerMetrics,
+    stats: &BusStats,
+) -> Result<usize, SendError> {
+    let mut out = String::<64>::new();
+    write!(&mut out, "{}", stats).unwrap();
+    send(socket, metrics, out.as_bytes())
 }
 
-impl Default for ConnectionState {
-    fn default() -> Self {
-        ConnectionState {
-            welcome: false,
-            mode: None,
+/// Send a `< isotprecv SRC DST DATA >` message, split into several writes.
+///
+/// Messages that do not fit in the send buffer are dropped; returns whether
+/// the message was sent.
+fn write_isotp(
+    socket: &mut Socket,
+    metrics: &mut ServerMetrics,
+    conf: &IsoTpConf,
+    data: &[u8],
+) -> Result<bool, SendError> {
+    let mut header = String::<32>::new();
+    write!(
+        &mut header,
+        "< isotprecv {} {} ",
+        CanId(conf.src_id),
+        CanId(conf.dst_id)
+    )
+    .unwrap();
+
+    let len = header.len() + 2 * data.len() + " >".len();
+    if data.len() > MAX_ISOTP_LEN
+        || socket.send_capacity() - socket.send_queue() < len
+    {
+        return Ok(false);
+    }
+
+    send(socket, metrics, header.as_bytes())?;
+
+    for chunk in data.chunks(32) {
+        let mut hex = String::<64>::new();
+        for byte in chunk {
+            write!(&mut hex, "{:02X}", byte).unwrap();
         }
+        send(socket, metrics, hex.as_bytes())?;
+    }
+
+    send(socket, metrics, " >".as_bytes())?;
+
+    Ok(true)
+}
+
+/// Error returned by [`Server::send_buslist_xml`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum BuslistError {
+    /// More than [`MAX_BUSES`] busses were given.
+    TooManyBuses,
+    /// The bus list could not be sent.
+    Send(SendError),
+}
+
+impl From<SendError> for BuslistError {
+    fn from(err: SendError) -> Self {
+        BuslistError::Send(err)
+    }
+}
+
+/// Callback set with one of the `Server::with_*` methods.
+struct Callback<'cb, F: ?Sized + 'cb>(&'cb mut F);
+
+impl<F: ?Sized> core::fmt::Debug for Callback<'_, F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("Callback")
     }
 }
 
+type OnRawFrame<'cb> = Callback<'cb, dyn FnMut(&socketcand::wire::Send) + 'cb>;
+type OnAnnounce<'cb> = Callback<'cb, dyn FnMut(&Announce) + 'cb>;
+
 /// Socketcand server.
+///
+/// `'cb` is the lifetime of the callbacks set with the `with_*` methods, such
+/// as [`Server::with_on_raw_frame`].
 #[derive(Debug)]
-pub struct Server {
+pub struct Server<'cb> {
     socket: SocketHandle,
-    port: u16,
+    config: ServerConfig,
     state: ConnectionState,
+    on_mode_change: Option<Callback<'cb, dyn FnMut(Mode, Mode)>>,
+    on_raw_frame: Option<OnRawFrame<'cb>>,
+    on_announce: Option<OnAnnounce<'cb>>,
+    get_state: Option<Callback<'cb, dyn FnMut() -> BusStateKind>>,
+    get_bitrate: Option<Callback<'cb, dyn FnMut() -> u32>>,
+    get_last_error: Option<Callback<'cb, dyn FnMut() -> u32>>,
+    on_sleep: Option<Callback<'cb, dyn FnMut()>>,
+    on_close: Option<Callback<'cb, dyn FnMut()>>,
+    on_wakeup: Option<Callback<'cb, dyn FnMut()>>,
+    /// local address to listen on, any if unset
+    listen_addr: Option<IpAddress>,
+    /// failed listen attempts since the last successful one
+    listen_failures: u8,
+    /// earliest time to try listening again after a failure
+    listen_retry_at: Option<Instant>,
+    /// last frame sent for each identifier, for `< request_frames >`
+    last_frames: FnvIndexMap<CanId, socketcand::wire::Frame, MAX_CACHED_FRAMES>,
 }
 
-impl Server {
+impl<'cb> Server<'cb> {
     /// Creates a new socketcand server.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `socket` is already open, see [`Server::new_with_config`].
     pub fn new<'a>(
         sockets: &mut SocketSet<'a>,
         socket: Socket<'a>,
         port: Port,
     ) -> Self {
+        Self::new_with_config(sockets, socket, ServerConfig::new(port))
+    }
+
+    /// Creates a new socketcand server with the given configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `socket` is already listening or connected. The server
+    /// listens on the socket itself and serves [`MAX_CONNECTIONS`] client.
+    pub fn new_with_config<'a>(
+        sockets: &mut SocketSet<'a>,
+        mut socket: Socket<'a>,
+        config: ServerConfig,
+    ) -> Self {
+        assert!(
+            !socket.is_open(),
+            "socket must be closed, a server listens on it itself and only \
+             accepts one connection"
+        );
+
+        socket.set_keep_alive(config.tcp_keepalive);
+
         let handle = sockets.add(socket);
 
         Self {
             socket: handle,
-            port: port.0,
+            config,
             state: ConnectionState::default(),
+            on_mode_change: None,
+            on_raw_frame: None,
+            on_announce: None,
+            get_state: None,
+            get_bitrate: None,
+            get_last_error: None,
+            on_sleep: None,
+            on_close: None,
+            on_wakeup: None,
+            listen_addr: None,
+            listen_failures: 0,
+            listen_retry_at: None,
+            last_frames: FnvIndexMap::new(),
+        }
+    }
+
+    /// Call `f` with the old and new mode whenever the connection changes
+    /// mode.
+    ///
+    /// Use this to start or stop hardware such as a CAN peripheral when the
+    /// client selects a mode.
+    pub fn with_on_mode_change(
+        mut self,
+        f: &'cb mut impl FnMut(Mode, Mode),
+    ) -> Self {
+        self.on_mode_change = Some(Callback(f));
+        self
+    }
+
+    /// Call `f` with each frame the client sends in raw mode.
+    ///
+    /// Use this to transmit the frames on the CAN bus. The `< send >`
+    /// command is still returned by [`Server::recv`].
+    pub fn with_on_raw_frame(
+        mut self,
+        f: &'cb mut impl FnMut(&socketcand::wire::Send),
+    ) -> Self {
+        self.on_raw_frame = Some(Callback(f));
+        self
+    }
+
+    /// Call `f` when a peer announces itself with `< announce >`.
+    ///
+    /// Use this to discover other gateways in a mesh network. The command is
+    /// still returned by [`Server::recv`].
+    pub fn with_on_announce(
+        mut self,
+        f: &'cb mut impl FnMut(&Announce),
+    ) -> Self {
+        self.on_announce = Some(Callback(f));
+        self
+    }
+
+    /// Call `f` when the client disconnects.
+    ///
+    /// Use this to release hardware resources, e.g. to put a CAN controller
+    /// into standby.
+    pub fn with_on_close(mut self, f: &'cb mut impl FnMut()) -> Self {
+        self.on_close = Some(Callback(f));
+        self
+    }
+
+    /// Call `f` when the client puts the bus to sleep with `< sleep >`.
+    ///
+    /// Use this to put the transceiver into its low power mode.
+    pub fn with_on_sleep(mut self, f: &'cb mut impl FnMut()) -> Self {
+        self.on_sleep = Some(Callback(f));
+        self
+    }
+
+    /// Call `f` when the client wakes the bus up with `< wakeup >`.
+    pub fn with_on_wakeup(mut self, f: &'cb mut impl FnMut()) -> Self {
+        self.on_wakeup = Some(Callback(f));
+        self
+    }
+
+    /// Set a function that reports the bus state in reply to
+    /// `< get_state >`.
+    ///
+    /// Without this the command is rejected with
+    /// `< nack reason="unsupported" >`.
+    pub fn with_get_state(
+        mut self,
+        f: &'cb mut impl FnMut() -> BusStateKind,
+    ) -> Self {
+        self.get_state = Some(Callback(f));
+        self
+    }
+
+    /// Set a function that reports the bit rate in bits per second in reply
+    /// to `< getbitrate >`.
+    ///
+    /// Without this the command is rejected with
+    /// `< nack reason="unsupported" >`.
+    pub fn with_get_bitrate(mut self, f: &'cb mut impl FnMut() -> u32) -> Self {
+        self.get_bitrate = Some(Callback(f));
+        self
+    }
+
+    /// Set a function that reports the last CAN controller error code in
+    /// reply to `< lasterror >`.
+    ///
+    /// Without this the command is rejected with
+    /// `< nack reason="unsupported" >`.
+    pub fn with_get_last_error(
+        mut self,
+        f: &'cb mut impl FnMut() -> u32,
+    ) -> Self {
+        self.get_last_error = Some(Callback(f));
+        self
+    }
+
+    /// Only accept connections to the given local address.
+    #[cfg(feature = "dual-stack")]
+    pub(crate) fn with_listen_addr(mut self, addr: IpAddress) -> Self {
+        self.listen_addr = Some(addr);
+        self
+    }
+
+    /// Returns the IP version the client is connected over.
+    #[cfg(feature = "dual-stack")]
+    pub(crate) fn client_stack(&self) -> Option<IpVersion> {
+        self.state.stack
+    }
+
+    /// Endpoint to listen for connections on.
+    fn listen_endpoint(&self) -> IpListenEndpoint {
+        IpListenEndpoint {
+            addr: self.listen_addr,
+            port: self.config.port.0,
+        }
+    }
+
+    /// Listen for a new connection, retrying as set by
+    /// [`ServerConfig::retry_listen`].
+    ///
+    /// # Panics
+    ///
+    /// Panics once all attempts set with [`ServerConfig::retry_listen`] have
+    /// failed. Without it, listening is retried on the next call.
+    fn listen(&mut self, socket: &mut Socket, now: Option<Instant>) {
+        match (self.listen_retry_at, now) {
+            (Some(at), Some(now)) if now < at => return,
+            // the retry delay cannot be checked without the current time
+            (Some(_), None) => return,
+            _ => {}
         }
+
+        // clear any heartbeat timeout set by the previous client
+        socket.set_timeout(None);
+
+        let Err(err) = socket.listen(self.listen_endpoint()) else {
+            self.listen_failures = 0;
+            self.listen_retry_at = None;
+            return;
+        };
+
+        let Some((attempts, delay_ms)) = self.config.listen_retry else {
+            #[cfg(feature = "defmt-03")]
+            defmt::warn!(
+                "Failed to listen on port {=u16}: {}",
+                self.config.port.0,
+                defmt::Debug2Format(&err),
+            );
+            return;
+        };
+
+        self.listen_failures = self.listen_failures.saturating_add(1);
+
+        #[cfg(feature = "defmt-03")]
+        defmt::warn!(
+            "Failed to listen on port {=u16}: {}, attempt {=u8} of {=u8}",
+            self.config.port.0,
+            defmt::Debug2Format(&err),
+            self.listen_failures,
+            attempts,
+        );
+
+        assert!(
+            self.listen_failures < attempts,
+            "failed to listen on port {}: {}",
+            self.config.port.0,
+            err
+        );
+
+        self.listen_retry_at =
+            now.map(|now| now + time::Duration::from_millis(delay_ms));
     }
 
     /// Perform socket lifecycle actions.
-    fn handle_socket(&mut self, socket: &mut Socket) {
-        if !socket.is_open() {
-            if !socket.is_listening() {
-                socket.listen(self.port).ok();
+    fn handle_socket(&mut self, socket: &mut Socket, now: Option<Instant>) {
+        if !socket.is_open() && !socket.is_listening() {
+            // the connection was reset or timed out without a FIN
+            if self.state.welcome {
+                self.end_connection();
             }
+
+            self.listen(socket, now);
         }
 
         // client has disconnected
         if socket.state() == State::CloseWait {
             socket.close();
-            // reset internal state
-            self.state = ConnectionState::default();
+            self.end_connection();
             return;
         }
 
-        if self.state.welcome == false && socket.can_send() {
+        if !self.state.welcome && socket.can_send() {
             // welcome message to client
-            socket.send_slice("< hi >".as_bytes()).ok();
+            send(socket, &mut self.state.metrics, "< hi >".as_bytes()).ok();
             self.state.welcome = true;
+            self.state.stats_interval = self.config.stats_interval;
+            self.state.stack = socket
+                .local_endpoint()
+                .map(|endpoint| endpoint.addr.version());
+        }
+    }
+
+    /// Reset the connection state once a client has disconnected, so nothing
+    /// carries over to the next client.
+    fn end_connection(&mut self) {
+        if let Some(Callback(on_close)) = &mut self.on_close {
+            on_close();
+        }
+
+        self.state = ConnectionState::default();
+    }
+
+    /// Abort the current connection and listen for a new one.
+    ///
+    /// Use this to recover from error states without recreating the server.
+    pub fn reset_connection(&mut self, sockets: &mut SocketSet) {
+        let socket = sockets.get_mut::<Socket>(self.socket);
+
+        socket.abort();
+        self.state = ConnectionState::default();
+        self.listen(socket, None);
+    }
+
+    /// Gracefully close the current connection.
+    ///
+    /// A TCP FIN is sent to the client; the server listens for a new
+    /// connection once the close completes.
+    pub fn close(&mut self, sockets: &mut SocketSet) {
+        let socket = sockets.get_mut::<Socket>(self.socket);
+
+        socket.close();
+        self.state = ConnectionState::default();
+    }
+
+    /// Returns `true` while a connection is being closed.
+    pub fn is_closing(&self, sockets: &SocketSet) -> bool {
+        let socket = sockets.get::<Socket>(self.socket);
+
+        matches!(
+            socket.state(),
+            State::FinWait1
+                | State::FinWait2
+                | State::Closing
+                | State::TimeWait
+                | State::LastAck
+        )
+    }
+
+    /// Returns the bus opened by the client with `< open >`.
+    pub fn opened_bus(&self) -> Option<Bus> {
+        self.state.opened_bus
+    }
+
+    /// Returns the socket for the bus opened by the client, if one was added
+    /// with [`ServerConfig::with_bus_socket`].
+    pub fn bus_socket(&self) -> Option<SocketHandle> {
+        self.state.bus_socket
+    }
+
+    /// Returns the busses this server was configured with, see
+    /// [`ServerConfig::with_buses`].
+    pub fn registered_buses(&self) -> &[Bus] {
+        &self.config.buses
+    }
+
+    /// Returns `true` if the bus may be used by the client.
+    ///
+    /// Any bus is accepted when no busses are registered.
+    fn is_registered(&self, bus: Bus) -> bool {
+        let buses = self.registered_buses();
+        buses.is_empty() || buses.contains(&bus)
+    }
+
+    /// Write the bus list response to the socket.
+    fn write_buslist(&mut self, socket: &mut Socket) -> Result<(), SendError> {
+        let mut out =
+            String::<{ 16 + MAX_BUSES * (MAX_BUS_NAME_LEN + 1) }>::new();
+
+        write!(&mut out, "< busses ").unwrap();
+        for bus in self.registered_buses() {
+            write!(&mut out, "{} ", bus).unwrap();
+        }
+        write!(&mut out, ">").unwrap();
+
+        send(socket, &mut self.state.metrics, out.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Send the list of busses provided by this server, e.g.
+    /// `< busses can0 can1 >`.
+    pub fn send_buslist(
+        &mut self,
+        sockets: &mut SocketSet,
+    ) -> Result<(), SendError> {
+        let socket = sockets.get_mut::<Socket>(self.socket);
+
+        self.handle_socket(socket, None);
+
+        if socket.may_send() {
+            self.write_buslist(socket)?;
+        }
+
+        Ok(())
+    }
+
+    /// Send a bus list in the XML format of a service discovery beacon, for
+    /// clients that expect it instead of `< busses ... >`.
+    ///
+    /// The `<URL>` tag holds the local address of the connection. Fails
+    /// without sending anything if more than [`MAX_BUSES`] busses are given.
+    pub fn send_buslist_xml(
+        &mut self,
+        sockets: &mut SocketSet,
+        busses: &[Bus],
+    ) -> Result<(), BuslistError> {
+        if busses.len() > MAX_BUSES {
+            return Err(BuslistError::TooManyBuses);
+        }
+
+        let socket = sockets.get_mut::<Socket>(self.socket);
+
+        self.handle_socket(socket, None);
+
+        if !socket.may_send() {
+            return Ok(());
+        }
+
+        let address =
+            socket.local_endpoint().map(|endpoint| match endpoint.addr {
+                IpAddress::Ipv4(ip) => IpAddr::V4(ip.0.into()),
+                IpAddress::Ipv6(ip) => IpAddr::V6(ip.0.into()),
+            });
+        let beacon = BeaconMessage::with_addresses(
+            "socketcand",
+            "SocketCAN",
+            address.as_slice(),
+            self.config.port.0,
+            busses,
+        );
+
+        // fits the header, an IPv6 URL and the longest name of every bus
+        let mut out = String::<1024>::new();
+        write!(&mut out, "{}", beacon).unwrap();
+        send(socket, &mut self.state.metrics, out.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Check a command against the configured authentication token.
+    ///
+    /// Returns `true` if the command should be processed further.
+    fn authorize(&mut self, socket: &mut Socket, cmd: &Command) -> bool {
+        let Some(token) = &self.config.auth_token else {
+            return true;
+        };
+
+        if let Command::Auth(auth) = cmd {
+            if constant_time_eq(auth.token.as_bytes(), token.as_bytes()) {
+                self.state.authenticated = true;
+                send(socket, &mut self.state.metrics, "< ok >".as_bytes()).ok();
+            } else {
+                send(
+                    socket,
+                    &mut self.state.metrics,
+                    "< error auth_failed >".as_bytes(),
+                )
+                .ok();
+            }
+            return false;
         }
+
+        if !self.state.authenticated {
+            send(
+                socket,
+                &mut self.state.metrics,
+                "< error not_authenticated >".as_bytes(),
+            )
+            .ok();
+            return false;
+        }
+
+        true
     }
 
     /// Register a waker for receive operations.
@@ -94,112 +729,1398 @@ impl Server {
     }
 
     /// Receive a command over the connection if there is any.
+    ///
+    /// Without the current time, listen attempts are not retried, echo
+    /// replies carry no elapsed time and loopback frames are timestamped
+    /// zero. Use [`Server::recv_at`] where a clock is available.
     pub fn recv<'a>(
         &'a mut self,
         sockets: &'a mut SocketSet,
+    ) -> Result<Option<Command>, RecvError> {
+        self.recv_inner(sockets, None)
+    }
+
+    /// Receive a command over the connection if there is any, at time `now`.
+    pub fn recv_at<'a>(
+        &'a mut self,
+        sockets: &'a mut SocketSet,
+        now: Instant,
+    ) -> Result<Option<Command>, RecvError> {
+        self.recv_inner(sockets, Some(now))
+    }
+
+    fn recv_inner(
+        &mut self,
+        sockets: &mut SocketSet,
+        now: Option<Instant>,
     ) -> Result<Option<Command>, RecvError> {
         let socket = sockets.get_mut::<Socket>(self.socket);
 
-        self.handle_socket(socket);
+        self.handle_socket(socket, now);
 
         if !socket.can_recv() || !socket.can_send() {
             return Ok(None);
         }
 
+        let mut non_ascii = false;
+        let queued = socket.recv_queue();
+
         let cmd = socket
             .recv(|data| {
+                if !data.iter().all(|&b| ascii::is_valid_command_byte(b)) {
+                    #[cfg(feature = "defmt-03")]
+                    defmt::error!("Received non-ASCII data");
+
+                    non_ascii = true;
+
+                    // clear receive buffer
+                    return (data.len(), None);
+                }
+
                 match from_utf8(data) {
                     Ok(ascii) => match command(ascii) {
                         Ok((remainder, cmd)) => {
                             let taken = data.len() - remainder.len();
-                            return (taken, Some(cmd));
-                        }
-                        Err(err) => {
-                            #[cfg(feature = "defmt-03")]
-                            defmt::error!(
-                                "Failed to parse command: {}",
-                                defmt::Debug2Format(&err),
-                            );
-
-                            // clear receive buffer
-                            return (data.len(), None);
+                            (taken, Some(cmd))
                         }
+                        Err(_err) => match command_padded(ascii) {
+                            Some((taken, cmd)) => (taken, Some(cmd)),
+                            None => {
+                                #[cfg(feature = "defmt-03")]
+                                defmt::error!(
+                                    "Failed to parse command: {}",
+                                    defmt::Debug2Format(&_err),
+                                );
+
+                                // clear receive buffer
+                                (data.len(), None)
+                            }
+                        },
                     },
-                    Err(err) => {
+                    Err(_err) => {
                         #[cfg(feature = "defmt-03")]
                         defmt::error!(
                             "Failed to convert command to utf8: {}",
-                            defmt::Debug2Format(&err)
+                            defmt::Debug2Format(&_err)
                         );
 
                         // clear receive buffer
-                        return (data.len(), None);
+                        (data.len(), None)
                     }
                 }
             })?
             .clone();
 
+        self.state.metrics.bytes_received +=
+            (queued - socket.recv_queue()) as u64;
+
+        if non_ascii {
+            send(
+                socket,
+                &mut self.state.metrics,
+                "< error non-ascii-data >".as_bytes(),
+            )
+            .ok();
+        }
+
         if let Some(ref cmd) = cmd {
+            self.state.metrics.commands_received += 1;
+            if now.is_some() {
+                self.state.last_activity = now;
+            }
+
+            // keep-alive, consumed without a response
+            if let Command::Nop(_) = cmd {
+                return Ok(None);
+            }
+
+            if !cmd.is_client_to_server() {
+                send(socket, &mut self.state.metrics, "< error >".as_bytes())
+                    .ok();
+                return Ok(None);
+            }
+
+            if !self.authorize(socket, cmd) {
+                return Ok(None);
+            }
+
+            let old_mode = self.state.mode.clone();
+
             match cmd {
-                Command::Open(_) => {
-                    self.state.mode = Some(Mode::Broadcast);
-                    socket.send_slice("< ok >".as_bytes()).ok();
+                Command::Open(open)
+                    if !self.is_registered(Bus::from(open.clone())) =>
+                {
+                    nack(socket, &mut self.state.metrics, "unknown_bus").ok();
+                    return Ok(None);
+                }
+                Command::Open(open) => {
+                    let bus = Bus::from(open.clone());
+                    self.state.opened_bus = Some(bus);
+                    self.state.bus_socket = self
+                        .config
+                        .bus_sockets
+                        .iter()
+                        .find(|(b, _)| *b == bus)
+                        .map(|(_, socket)| *socket);
+                    self.state.mode =
+                        open.mode.clone().unwrap_or(Mode::Broadcast);
+                    self.state.broadcast_remaining = None;
+                    send(socket, &mut self.state.metrics, "< ok >".as_bytes())
+                        .ok();
+                }
+                Command::RawMode(raw) => {
+                    self.state.mode = Mode::Raw;
+                    self.state.loopback = raw.loopback;
+                    send(socket, &mut self.state.metrics, "< ok >".as_bytes())
+                        .ok();
                 }
-                Command::RawMode(_) => {
-                    self.state.mode = Some(Mode::Raw);
-                    socket.send_slice("< ok >".as_bytes()).ok();
+                Command::Send(frame) if self.state.mode == Mode::Raw => {
+                    if let Some(Callback(on_raw_frame)) = &mut self.on_raw_frame
+                    {
+                        on_raw_frame(frame);
+                    }
+
+                    if self.state.loopback {
+                        let mut out = String::<128>::new();
+                        let timestamp = now.map_or(Duration::ZERO, |now| {
+                            Duration::from_micros(now.total_micros() as u64)
+                        });
+
+                        format_frame(
+                            &mut out,
+                            frame.id,
+                            timestamp,
+                            &frame.data,
+                        )
+                        .unwrap();
+
+                        match send(
+                            socket,
+                            &mut self.state.metrics,
+                            out.as_bytes(),
+                        ) {
+                            Ok(_) => self.state.metrics.frames_sent += 1,
+                            Err(_) => self.state.metrics.frames_dropped += 1,
+                        }
+                    }
                 }
-                Command::BroadcastMode(_) => {
-                    self.state.mode = Some(Mode::Broadcast);
-                    socket.send_slice("< ok >".as_bytes()).ok();
+                Command::BroadcastMode(broadcast) => {
+                    self.state.mode = Mode::Broadcast;
+                    self.state.broadcast_remaining = broadcast.count;
+                    send(socket, &mut self.state.metrics, "< ok >".as_bytes())
+                        .ok();
+                }
+                Command::ControlMode(control)
+                    if !self.registered_buses().is_empty()
+                        && control.bus.as_deref().is_some_and(|name| {
+                            !Bus::from_str_case_insensitive(name)
+                                .is_some_and(|bus| self.is_registered(bus))
+                        }) =>
+                {
+                    nack(socket, &mut self.state.metrics, "unknown_bus").ok();
+                    return Ok(None);
                 }
                 Command::ControlMode(_) => {
-                    self.state.mode = Some(Mode::Control);
-                    socket.send_slice("< ok >".as_bytes()).ok();
+                    self.state.mode = Mode::Control;
+                    send(socket, &mut self.state.metrics, "< ok >".as_bytes())
+                        .ok();
                 }
-                _ => {}
-            }
-        }
+                Command::IsoTpMode(_) => {
+                    self.state.mode = Mode::IsoTp;
+                    send(socket, &mut self.state.metrics, "< ok >".as_bytes())
+                        .ok();
+                }
+                Command::Auth(_) => {
+                    send(socket, &mut self.state.metrics, "< ok >".as_bytes())
+                        .ok();
+                }
+                Command::Add(add) => {
+                    if self.has_job(add.id) {
+                        nack(socket, &mut self.state.metrics, "duplicate_job")
+                            .ok();
+                        return Ok(None);
+                    }
 
-        Ok(cmd)
-    }
+                    let inserted = self.state.jobs.insert(add.clone());
 
-    /// Send a CAN frame.
-    pub fn send_frame(
-        &mut self,
-        sockets: &mut SocketSet,
-        now: Instant,
-        frame: &impl Frame,
-    ) -> Result<(), SendError> {
-        let socket = sockets.get_mut::<Socket>(self.socket);
+                    if inserted.is_err() {
+                        nack(socket, &mut self.state.metrics, "table_full")
+                            .ok();
+                        return Ok(None);
+                    }
+                }
+                Command::AddCount(add) => {
+                    if self.has_job(add.id) {
+                        nack(socket, &mut self.state.metrics, "duplicate_job")
+                            .ok();
+                        return Ok(None);
+                    }
 
-        self.handle_socket(socket);
+                    let inserted =
+                        self.state.jobs.insert(Add::from(add.clone()));
+
+                    if inserted.is_err() {
+                        nack(socket, &mut self.state.metrics, "table_full")
+                            .ok();
+                        return Ok(None);
+                    }
 
-        if socket.may_send() && self.state.mode == Some(Mode::Raw) {
-            let mut out = String::<128>::new();
+                    // both tables have the same capacity
+                    self.state.job_counts.insert(CanId(add.id), add.count).ok();
+                }
+                Command::Update(update) => {
+                    let Some(job) = self.state.jobs.get_mut(update.id) else {
+                        nack(socket, &mut self.state.metrics, "not_found").ok();
+                        return Ok(None);
+                    };
 
-            write!(&mut out, "< frame ",).unwrap();
+                    job.dlc = update.dlc;
+                    job.data = update.data.clone();
+                }
+                Command::Delete(delete) => {
+                    if !self.has_job(delete.id) {
+                        nack(socket, &mut self.state.metrics, "not_found").ok();
+                        return Ok(None);
+                    }
 
-            match frame.id() {
-                embedded_can::Id::Standard(id) => {
-                    write!(&mut out, "{:03X} ", id.as_raw()).unwrap()
+                    self.state.jobs.remove(delete.id);
+                    self.state.job_counts.remove(&CanId(delete.id));
                 }
-                embedded_can::Id::Extended(id) => {
-                    write!(&mut out, "{:08X} ", id.as_raw()).unwrap()
+                Command::DeleteRange(range) => {
+                    self.state.jobs.retain(|job| !range.contains(job.id));
+                    self.state.job_counts.retain(|id, _| !range.contains(id.0));
                 }
-            };
+                Command::BcmRxSetup(setup) => {
+                    let filters = &mut self.state.rx_filters;
 
-            write!(&mut out, "{}.{} ", now.secs(), now.millis()).unwrap();
+                    if let Some(filter) =
+                        filters.iter_mut().find(|f| f.id == setup.id)
+                    {
+                        *filter = setup.clone();
+                    } else if filters.push(setup.clone()).is_err() {
+                        nack(socket, &mut self.state.metrics, "table_full")
+                            .ok();
+                        return Ok(None);
+                    }
+                }
+                Command::Statistics(stats) => {
+                    // an interval of zero disables periodic statistics and
+                    // requests a single report instead
+                    self.state.stats_interval = Some(stats.interval)
+                        .filter(|interval| !interval.is_zero());
+                    self.state.stats_requested = stats.interval.is_zero();
+                    self.state.next_stats_at = None;
+                }
+                Command::Echo(echo) if echo.nonce.is_some() => {
+                    // replied to before this call returns, so no time has
+                    // passed on the `now` clock since the command was parsed
+                    let reply = Echo {
+                        nonce: echo.nonce.clone(),
+                        elapsed: now.map(|_| Duration::ZERO),
+                    };
 
-            for byte in frame.data() {
-                write!(&mut out, "{:02X}", byte).unwrap();
-            }
+                    write_echo(socket, &mut self.state.metrics, &reply);
+                }
+                Command::Echo(_) => {
+                    let mut out = String::<64>::new();
 
-            write!(&mut out, " >").unwrap();
+                    match self.config.version {
+                        // lets clients detect the server version
+                        Some(version) => {
+                            write!(&mut out, "< echo version={} >", version)
+                        }
+                        None => write!(&mut out, "< echo >"),
+                    }
+                    .unwrap();
 
-            socket.send_slice(out.as_bytes())?;
-        }
+                    send(socket, &mut self.state.metrics, out.as_bytes()).ok();
+                }
+                Command::GetBusses(_)
+                    if !self.registered_buses().is_empty() =>
+                {
+                    self.write_buslist(socket).ok();
+                }
+                Command::GetState(_) => {
+                    let Some(Callback(get_state)) = &mut self.get_state else {
+                        nack(socket, &mut self.state.metrics, "unsupported")
+                            .ok();
+                        return Ok(None);
+                    };
+
+                    let mut out = String::<32>::new();
+                    write!(&mut out, "{}", BusState { state: get_state() })
+                        .unwrap();
+                    send(socket, &mut self.state.metrics, out.as_bytes()).ok();
+                }
+                Command::GetBitrate(_) => {
+                    let Some(Callback(get_bitrate)) = &mut self.get_bitrate
+                    else {
+                        nack(socket, &mut self.state.metrics, "unsupported")
+                            .ok();
+                        return Ok(None);
+                    };
+
+                    let mut out = String::<32>::new();
+                    write!(
+                        &mut out,
+                        "{}",
+                        Bitrate {
+                            rate: get_bitrate()
+                        }
+                    )
+                    .unwrap();
+                    send(socket, &mut self.state.metrics, out.as_bytes()).ok();
+                }
+                Command::LastError(_) => {
+                    let Some(Callback(get_last_error)) =
+                        &mut self.get_last_error
+                    else {
+                        nack(socket, &mut self.state.metrics, "unsupported")
+                            .ok();
+                        return Ok(None);
+                    };
+
+                    let code = get_last_error();
+                    let mut out = String::<32>::new();
+                    write!(&mut out, "{}", LastErrorCode { code }).unwrap();
+                    send(socket, &mut self.state.metrics, out.as_bytes()).ok();
+                }
+                Command::Sleep(_) => {
+                    self.state.sleeping = true;
+
+                    if let Some(Callback(on_sleep)) = &mut self.on_sleep {
+                        on_sleep();
+                    }
+
+                    send(socket, &mut self.state.metrics, "< ok >".as_bytes())
+                        .ok();
+                }
+                Command::Wakeup(_) if !self.state.sleeping => {
+                    nack(socket, &mut self.state.metrics, "not_sleeping").ok();
+                    return Ok(None);
+                }
+                Command::Wakeup(_) => {
+                    self.state.sleeping = false;
+
+                    if let Some(Callback(on_wakeup)) = &mut self.on_wakeup {
+                        on_wakeup();
+                    }
+
+                    send(socket, &mut self.state.metrics, "< ok >".as_bytes())
+                        .ok();
+                }
+                Command::GetVersion(_) => {
+                    let Some(version) = self.config.version else {
+                        nack(socket, &mut self.state.metrics, "unsupported")
+                            .ok();
+                        return Ok(None);
+                    };
+
+                    let mut out = String::<48>::new();
+                    write!(&mut out, "< version {} >", version).unwrap();
+                    send(socket, &mut self.state.metrics, out.as_bytes()).ok();
+                }
+                Command::HeartbeatConfig(heartbeat) => {
+                    // abort the connection if the client stops responding
+                    // for longer than the heartbeat interval
+                    let timeout = Some(heartbeat.interval)
+                        .filter(|interval| !interval.is_zero())
+                        .map(|interval| {
+                            time::Duration::from_micros(
+                                interval.as_micros() as u64
+                            )
+                        });
+
+                    socket.set_timeout(timeout);
+                    send(socket, &mut self.state.metrics, "< ok >".as_bytes())
+                        .ok();
+                }
+                Command::SubscribeTx(subscribe) => {
+                    let subscriptions = &mut self.state.tx_subscriptions;
+
+                    if !subscriptions.contains(&subscribe.id)
+                        && subscriptions.push(subscribe.id).is_err()
+                    {
+                        nack(socket, &mut self.state.metrics, "table_full")
+                            .ok();
+                        return Ok(None);
+                    }
+
+                    send(socket, &mut self.state.metrics, "< ok >".as_bytes())
+                        .ok();
+                }
+                Command::Announce(announce) => {
+                    if let Some(Callback(on_announce)) = &mut self.on_announce {
+                        on_announce(announce);
+                    }
+                }
+                Command::RequestFrames(request) => {
+                    let Some(frame) = self.last_frames.get(&CanId(request.id))
+                    else {
+                        nack(socket, &mut self.state.metrics, "not_found").ok();
+                        return Ok(None);
+                    };
+
+                    let mut out = String::<128>::new();
+                    write!(&mut out, "{}", frame).unwrap();
+                    send(socket, &mut self.state.metrics, out.as_bytes()).ok();
+                }
+                Command::IsoTpConf(conf) => {
+                    self.state.iso_tp_conf = Some(conf.clone());
+                    send(socket, &mut self.state.metrics, "< ok >".as_bytes())
+                        .ok();
+                }
+                Command::ListJobs(_) => {
+                    for add in self.state.jobs.iter() {
+                        let mut out = String::<128>::new();
+                        write!(&mut out, "{}", Job::from(add.clone())).unwrap();
+                        send(socket, &mut self.state.metrics, out.as_bytes())
+                            .ok();
+                    }
+
+                    let mut out = String::<16>::new();
+                    write!(&mut out, "{}", EndList).unwrap();
+                    send(socket, &mut self.state.metrics, out.as_bytes()).ok();
+                }
+                _ => {}
+            }
+
+            if self.state.mode != old_mode {
+                #[cfg(feature = "defmt-03")]
+                defmt::debug!(
+                    "Mode changed from {=str} to {=str}",
+                    old_mode.as_str(),
+                    self.state.mode.as_str(),
+                );
+
+                if let Some(Callback(on_mode_change)) = &mut self.on_mode_change
+                {
+                    on_mode_change(old_mode, self.state.mode.clone());
+                }
+            }
+        }
+
+        Ok(cmd)
+    }
+
+    /// Returns the throughput counters for the current connection.
+    pub fn metrics(&self) -> &ServerMetrics {
+        &self.state.metrics
+    }
+
+    /// Returns when the last command was received from the client, if any.
+    pub fn last_activity(&self) -> Option<Instant> {
+        self.state.last_activity
+    }
+
+    /// Check whether a cyclic job exists for the given CAN identifier.
+    pub fn has_job(&self, id: Id) -> bool {
+        self.state.jobs.contains(id)
+    }
+
+    /// Returns the receive filters set up by the client with `< rx_setup >`.
+    pub fn rx_filters(&self) -> &[BcmRxSetup] {
+        &self.state.rx_filters
+    }
+
+    /// Record that a cyclic job was transmitted.
+    ///
+    /// Call this after each transmission of a job; jobs added with
+    /// `< add_count >` are deleted once they have been sent `COUNT` times.
+    /// If broadcast mode was entered with `< bcmode COUNT >`, all jobs are
+    /// deleted once `COUNT` transmissions have been made in total.
+    pub fn job_transmitted(&mut self, id: Id) {
+        if self.state.mode == Mode::Broadcast {
+            if let Some(remaining) = &mut self.state.broadcast_remaining {
+                *remaining = remaining.saturating_sub(1);
+
+                if *remaining == 0 {
+                    self.state.broadcast_remaining = None;
+                    self.state.jobs.retain(|_| false);
+                    self.state.job_counts.clear();
+                    return;
+                }
+            }
+        }
+
+        let key = CanId(id);
+
+        let Some(count) = self.state.job_counts.get_mut(&key) else {
+            return;
+        };
+
+        *count = count.saturating_sub(1);
+
+        if *count == 0 {
+            self.state.job_counts.remove(&key);
+            self.state.jobs.remove(id);
+        }
+    }
+
+    /// Receive all pending commands.
+    ///
+    /// Returns once the receive buffer is empty or `commands` is full.
+    pub fn recv_all<const N: usize>(
+        &mut self,
+        sockets: &mut SocketSet,
+        commands: &mut Vec<Command, N>,
+    ) -> Result<(), RecvError> {
+        self.recv_all_inner(sockets, None, commands)
+    }
+
+    /// Receive all pending commands at time `now`.
+    ///
+    /// Returns once the receive buffer is empty or `commands` is full.
+    pub fn recv_all_at<const N: usize>(
+        &mut self,
+        sockets: &mut SocketSet,
+        now: Instant,
+        commands: &mut Vec<Command, N>,
+    ) -> Result<(), RecvError> {
+        self.recv_all_inner(sockets, Some(now), commands)
+    }
+
+    fn recv_all_inner<const N: usize>(
+        &mut self,
+        sockets: &mut SocketSet,
+        now: Option<Instant>,
+        commands: &mut Vec<Command, N>,
+    ) -> Result<(), RecvError> {
+        while !commands.is_full() {
+            let queued = sockets.get::<Socket>(self.socket).recv_queue();
+
+            if let Some(cmd) = self.recv_inner(sockets, now)? {
+                commands.push(cmd).ok();
+                continue;
+            }
+
+            // stop unless a rejected command was consumed and more data is
+            // waiting
+            let socket = sockets.get::<Socket>(self.socket);
+            if !socket.can_recv() || socket.recv_queue() == queued {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Receive all pending commands and pass each to `handler`.
+    ///
+    /// An alternative to [`Server::recv`] that avoids matching on the
+    /// returned commands.
+    pub fn handle(
+        &mut self,
+        sockets: &mut SocketSet,
+        now: Instant,
+        handler: &mut impl CommandHandler,
+    ) -> Result<(), RecvError> {
+        loop {
+            let queued = sockets.get::<Socket>(self.socket).recv_queue();
+
+            if let Some(cmd) = self.recv_at(sockets, now)? {
+                dispatch(handler, &cmd);
+                continue;
+            }
+
+            // stop unless a rejected command was consumed and more data is
+            // waiting
+            let socket = sockets.get::<Socket>(self.socket);
+            if !socket.can_recv() || socket.recv_queue() == queued {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Send a CAN frame.
+    ///
+    /// Frames are only forwarded in raw mode. In ISO-TP mode pass reassembled
+    /// messages to [`Server::send_isotp`] instead.
+    ///
+    /// In any mode, frames with an identifier the client subscribed to with
+    /// `< subscribe_tx >` are also echoed as `< txecho ID DLC DATA >`, unless
+    /// they are CAN FD frames with more than 8 data bytes. The last frame for
+    /// each identifier is kept for `< request_frames ID >`.
+    ///
+    /// Tests can call this to simulate frames received from the CAN bus
+    /// without a CAN peripheral.
+    pub fn send_frame(
+        &mut self,
+        sockets: &mut SocketSet,
+        now: Instant,
+        frame: &impl Frame,
+    ) -> Result<(), SendError> {
+        let socket = sockets.get_mut::<Socket>(self.socket);
+
+        self.handle_socket(socket, Some(now));
+        self.forward_frame(socket, now, frame)?;
+
+        Ok(())
+    }
+
+    /// Forward a frame to a connected client, see [`Server::send_frame`].
+    ///
+    /// Returns whether the frame was sent.
+    fn forward_frame(
+        &mut self,
+        socket: &mut Socket,
+        now: Instant,
+        frame: &impl Frame,
+    ) -> Result<bool, SendError> {
+        self.cache_frame(now, frame);
+
+        let subscribed = self.state.tx_subscriptions.contains(&frame.id());
+
+        // CAN FD data does not fit in a txecho
+        if let (true, Ok(data)) = (subscribed, Vec::from_slice(frame.data())) {
+            let echo = TxEcho {
+                id: frame.id(),
+                dlc: data.len() as u8,
+                data,
+            };
+
+            // the frame is still forwarded if the echo cannot be sent
+            let mut out = String::<64>::new();
+            write!(&mut out, "{}", echo).unwrap();
+            send(socket, &mut self.state.metrics, out.as_bytes()).ok();
+        }
+
+        if self.state.mode != Mode::Raw {
+            return Ok(false);
+        }
+
+        if !socket.may_send() {
+            self.state.metrics.frames_dropped += 1;
+            return Ok(false);
+        }
+
+        let mut out = String::<128>::new();
+        let timestamp = Duration::from_micros(now.total_micros() as u64);
+
+        format_frame(&mut out, frame.id(), timestamp, frame.data()).unwrap();
+
+        if let Err(err) = send(socket, &mut self.state.metrics, out.as_bytes())
+        {
+            self.state.metrics.frames_dropped += 1;
+            return Err(err);
+        }
+
+        self.state.metrics.frames_sent += 1;
+
+        Ok(true)
+    }
+
+    /// Send several CAN frames with a single write to the socket.
+    ///
+    /// In raw mode the `< frame >` messages are concatenated and queued at
+    /// once, so they leave in fewer TCP segments than with repeated calls to
+    /// [`Server::send_frame`]. The batch stops at the first frame that does
+    /// not fit in the socket send buffer, or in 1024 bytes. When the client
+    /// subscribed to echoes, each frame is forwarded as by
+    /// [`Server::send_frame`] instead.
+    ///
+    /// Returns the number of frames sent. In raw mode these are the first
+    /// frames of `frames`; the rest were not sent, and can be passed again
+    /// once the socket has room.
+    pub fn send_batch(
+        &mut self,
+        sockets: &mut SocketSet,
+        now: Instant,
+        frames: &[&impl Frame],
+    ) -> Result<usize, SendError> {
+        let socket = sockets.get_mut::<Socket>(self.socket);
+
+        self.handle_socket(socket, Some(now));
+
+        if !self.state.tx_subscriptions.is_empty() {
+            let mut count = 0;
+
+            for frame in frames {
+                if self.forward_frame(socket, now, *frame)? {
+                    count += 1;
+                }
+            }
+
+            return Ok(count);
+        }
+
+        for frame in frames {
+            self.cache_frame(now, *frame);
+        }
+
+        if self.state.mode != Mode::Raw {
+            return Ok(0);
+        }
+
+        if !socket.may_send() {
+            self.state.metrics.frames_dropped += frames.len() as u64;
+            return Ok(0);
+        }
+
+        let room = socket.send_capacity() - socket.send_queue();
+        let timestamp = Duration::from_micros(now.total_micros() as u64);
+        let mut out = String::<MAX_BATCH_LEN>::new();
+        let mut count = 0;
+
+        for frame in frames {
+            let len = out.len();
+
+            if format_frame(&mut out, frame.id(), timestamp, frame.data())
+                .is_err()
+                || out.len() > room
+            {
+                out.truncate(len);
+                break;
+            }
+
+            count += 1;
+        }
+
+        if count == 0 {
+            return Ok(0);
+        }
+
+        if let Err(err) = send(socket, &mut self.state.metrics, out.as_bytes())
+        {
+            self.state.metrics.frames_dropped += count as u64;
+            return Err(err);
+        }
+
+        self.state.metrics.frames_sent += count as u64;
+
+        Ok(count)
+    }
+
+    /// Remember `frame` as the last one sent with its identifier, evicting
+    /// another identifier if the cache is full.
+    ///
+    /// CAN FD frames are not cached, as the reply only carries classic CAN
+    /// data.
+    fn cache_frame(&mut self, now: Instant, frame: &impl Frame) {
+        let Ok(data) = Vec::from_slice(frame.data()) else {
+            return;
+        };
+
+        let key = CanId(frame.id());
+
+        if self.last_frames.len() == self.last_frames.capacity()
+            && !self.last_frames.contains_key(&key)
+        {
+            if let Some(evict) = self.last_frames.keys().next().copied() {
+                self.last_frames.remove(&evict);
+            }
+        }
+
+        // there is room for the identifier after evicting
+        self.last_frames
+            .insert(
+                key,
+                socketcand::wire::Frame {
+                    id: frame.id(),
+                    timestamp: Duration::from_micros(now.total_micros() as u64),
+                    data,
+                },
+            )
+            .ok();
+    }
+
+    /// Send a received ISO-TP message to the client as
+    /// `< isotprecv SRC DST DATA >`.
+    ///
+    /// Messages are only sent in ISO-TP mode once the client has configured
+    /// addressing with `< isotpconf >`. Messages longer than 4095 bytes, or
+    /// that do not fit in the socket send buffer, are dropped.
+    pub fn send_isotp(
+        &mut self,
+        sockets: &mut SocketSet,
+        now: Instant,
+        data: &[u8],
+    ) -> Result<(), SendError> {
+        let socket = sockets.get_mut::<Socket>(self.socket);
+
+        self.handle_socket(socket, Some(now));
+
+        if self.state.mode != Mode::IsoTp {
+            return Ok(());
+        }
+
+        let Some(conf) = &self.state.iso_tp_conf else {
+            return Ok(());
+        };
+
+        if !socket.may_send()
+            || !write_isotp(socket, &mut self.state.metrics, conf, data)?
+        {
+            self.state.metrics.frames_dropped += 1;
+            return Ok(());
+        }
+
+        self.state.metrics.frames_sent += 1;
 
         Ok(())
     }
+
+    /// Send a controller error frame.
+    ///
+    /// [`embedded_can::Frame`] cannot represent error frames, so they are
+    /// passed separately from [`Server::send_frame`]. Like regular frames,
+    /// they are only forwarded in raw mode.
+    pub fn send_error_frame(
+        &mut self,
+        sockets: &mut SocketSet,
+        now: Instant,
+        error_class: u8,
+        error_data: [u8; MAX_DATA_LEN],
+    ) -> Result<(), SendError> {
+        let socket = sockets.get_mut::<Socket>(self.socket);
+
+        self.handle_socket(socket, Some(now));
+
+        if self.state.mode != Mode::Raw {
+            return Ok(());
+        }
+
+        if !socket.may_send() {
+            self.state.metrics.frames_dropped += 1;
+            return Ok(());
+        }
+
+        let error = FrameError {
+            timestamp: Duration::from_micros(now.total_micros() as u64),
+            error_class,
+            error_data,
+        };
+
+        let mut out = String::<64>::new();
+        write!(&mut out, "{}", error).unwrap();
+
+        if let Err(err) = send(socket, &mut self.state.metrics, out.as_bytes())
+        {
+            self.state.metrics.frames_dropped += 1;
+            return Err(err);
+        }
+
+        self.state.metrics.frames_sent += 1;
+
+        Ok(())
+    }
+
+    /// Send raw bytes to the client, e.g. for non-standard protocol
+    /// extensions.
+    ///
+    /// The data is written directly to the socket buffer and bypasses all
+    /// protocol validation, including the current mode and authentication.
+    ///
+    /// Returns `Ok(false)` without sending anything if the data does not fit
+    /// in the socket send buffer, so a message is never cut short.
+    pub fn send_raw(
+        &mut self,
+        sockets: &mut SocketSet,
+        data: &[u8],
+    ) -> Result<bool, SendError> {
+        let socket = sockets.get_mut::<Socket>(self.socket);
+
+        self.handle_socket(socket, None);
+
+        if socket.send_capacity() - socket.send_queue() < data.len() {
+            return Ok(false);
+        }
+
+        send(socket, &mut self.state.metrics, data)?;
+
+        Ok(true)
+    }
+
+    /// Send bus statistics if the reporting interval has elapsed.
+    ///
+    /// Call this periodically with the latest statistics; nothing is sent
+    /// unless statistics were requested by the client or enabled with
+    /// [`ServerConfig::with_statistics_interval`]. A one-off `< statistics 0 >`
+    /// request is answered on the next call.
+    pub fn poll_statistics(
+        &mut self,
+        sockets: &mut SocketSet,
+        now: Instant,
+        stats: &BusStats,
+    ) -> Result<(), SendError> {
+        let socket = sockets.get_mut::<Socket>(self.socket);
+
+        self.handle_socket(socket, Some(now));
+
+        if self.state.stats_requested && socket.may_send() {
+            write_stats(socket, &mut self.state.metrics, stats)?;
+            self.state.stats_requested = false;
+        }
+
+        let Some(interval) = self.state.stats_interval else {
+            return Ok(());
+        };
+
+        let interval = time::Duration::from_micros(interval.as_micros() as u64);
+
+        match self.state.next_stats_at {
+            Some(due) if now >= due && socket.may_send() => {
+                write_stats(socket, &mut self.state.metrics, stats)?;
+
+                self.state.next_stats_at = Some(now + interval);
+            }
+            Some(_) => {}
+            None => self.state.next_stats_at = Some(now + interval),
+        }
+
+        Ok(())
+    }
+
+    /// Send bus statistics to the client immediately.
+    ///
+    /// Unlike [`Server::poll_statistics`] this ignores the reporting
+    /// interval.
+    pub fn send_statistics(
+        &mut self,
+        sockets: &mut SocketSet,
+        stats: &BusStats,
+    ) -> Result<(), SendError> {
+        let socket = sockets.get_mut::<Socket>(self.socket);
+
+        self.handle_socket(socket, None);
+
+        write_stats(socket, &mut self.state.metrics, stats)?;
+        self.state.stats_requested = false;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smoltcp::{
+        iface::{Config, Interface, SocketStorage},
+        phy::{self, Device, DeviceCapabilities, Medium},
+        socket::tcp::SocketBuffer,
+        wire::{HardwareAddress, IpCidr},
+    };
+    use std::{boxed::Box, collections::VecDeque, string::String, vec};
+
+    const LOCALHOST: IpAddress = IpAddress::v4(127, 0, 0, 1);
+
+    /// Loopback device, like `smoltcp::phy::Loopback` but without needing
+    /// smoltcp's `alloc` feature.
+    #[derive(Default)]
+    struct Loopback {
+        queue: VecDeque<vec::Vec<u8>>,
+    }
+
+    struct RxToken(vec::Vec<u8>);
+
+    struct TxToken<'a>(&'a mut VecDeque<vec::Vec<u8>>);
+
+    impl phy::RxToken for RxToken {
+        fn consume<R, F>(mut self, f: F) -> R
+        where
+            F: FnOnce(&mut [u8]) -> R,
+        {
+            f(&mut self.0)
+        }
+    }
+
+    impl phy::TxToken for TxToken<'_> {
+        fn consume<R, F>(self, len: usize, f: F) -> R
+        where
+            F: FnOnce(&mut [u8]) -> R,
+        {
+            let mut packet = vec![0; len];
+            let result = f(&mut packet);
+            self.0.push_back(packet);
+            result
+        }
+    }
+
+    impl Device for Loopback {
+        type RxToken<'a> = RxToken;
+        type TxToken<'a> = TxToken<'a>;
+
+        fn receive(
+            &mut self,
+            _timestamp: Instant,
+        ) -> Option<(RxToken, TxToken<'_>)> {
+            let packet = self.queue.pop_front()?;
+            Some((RxToken(packet), TxToken(&mut self.queue)))
+        }
+
+        fn transmit(&mut self, _timestamp: Instant) -> Option<TxToken<'_>> {
+            Some(TxToken(&mut self.queue))
+        }
+
+        fn capabilities(&self) -> DeviceCapabilities {
+            let mut caps = DeviceCapabilities::default();
+            caps.medium = Medium::Ip;
+            caps.max_transmission_unit = 1500;
+            caps
+        }
+    }
+
+    /// A server and a client connected over a loopback interface.
+    struct Harness {
+        device: Loopback,
+        iface: Interface,
+        sockets: SocketSet<'static>,
+        server: Server<'static>,
+        client: SocketHandle,
+        now: Instant,
+    }
+
+    fn tcp_socket() -> Socket<'static> {
+        let buffer = || SocketBuffer::new(Box::leak(vec![0; 4096].into()));
+        Socket::new(buffer(), buffer())
+    }
+
+    impl Harness {
+        /// Connect a client and consume the `< hi >` welcome message.
+        fn connect(config: ServerConfig) -> Self {
+            let now = Instant::ZERO;
+            let mut device = Loopback::default();
+            let mut iface = Interface::new(
+                Config::new(HardwareAddress::Ip),
+                &mut device,
+                now,
+            );
+            iface.update_ip_addrs(|addrs| {
+                addrs.push(IpCidr::new(LOCALHOST, 8)).unwrap();
+            });
+
+            let storage = Box::leak(Box::new([SocketStorage::EMPTY; 4]));
+            let mut sockets = SocketSet::new(&mut storage[..]);
+            let server =
+                Server::new_with_config(&mut sockets, tcp_socket(), config);
+            let client = sockets.add(tcp_socket());
+
+            let mut harness = Self {
+                device,
+                iface,
+                sockets,
+                server,
+                client,
+                now,
+            };
+
+            // start listening
+            harness.recv();
+            harness.connect_client(49152);
+
+            harness
+        }
+
+        /// Connect the client from `local_port` and consume the `< hi >`
+        /// welcome message.
+        fn connect_client(&mut self, local_port: u16) {
+            let port = self.server.config.port.0;
+            let socket = self.sockets.get_mut::<Socket>(self.client);
+            socket
+                .connect(self.iface.context(), (LOCALHOST, port), local_port)
+                .unwrap();
+            self.poll();
+            self.recv();
+
+            assert_eq!(self.read(), "< hi >");
+        }
+
+        /// Advance time and exchange packets until the interface is idle.
+        fn poll(&mut self) {
+            for _ in 0..8 {
+                self.now += time::Duration::from_millis(1);
+                self.iface
+                    .poll(self.now, &mut self.device, &mut self.sockets);
+            }
+        }
+
+        /// Send `data` from the client to the server.
+        fn write(&mut self, data: &str) {
+            let socket = self.sockets.get_mut::<Socket>(self.client);
+            socket.send_slice(data.as_bytes()).unwrap();
+            self.poll();
+        }
+
+        /// Receive a command on the server.
+        fn recv(&mut self) -> Option<Command> {
+            let cmd = self.server.recv_at(&mut self.sockets, self.now).unwrap();
+            self.poll();
+            cmd
+        }
+
+        /// Pass a frame received from the CAN bus to the server.
+        fn send_frame(&mut self, frame: &socketcand::wire::Frame) {
+            self.server
+                .send_frame(&mut self.sockets, self.now, frame)
+                .unwrap();
+        }
+
+        /// Read everything the server sent to the client.
+        fn read(&mut self) -> String {
+            self.poll();
+
+            let socket = self.sockets.get_mut::<Socket>(self.client);
+            let mut out = String::new();
+            while socket.can_recv() {
+                socket
+                    .recv(|data| {
+                        out.push_str(from_utf8(data).unwrap());
+                        (data.len(), ())
+                    })
+                    .unwrap();
+            }
+            out
+        }
+    }
+
+    #[test]
+    fn echo_nonces_in_one_read() {
+        let mut harness = Harness::connect(ServerConfig::default());
+
+        harness.write("< echo a >< echo b >");
+
+        let mut commands = Vec::<Command, 4>::new();
+        harness
+            .server
+            .recv_all_at(&mut harness.sockets, harness.now, &mut commands)
+            .unwrap();
+
+        assert_eq!(commands.len(), 2);
+        assert_eq!(harness.read(), "< echo a 0 >< echo b 0 >");
+    }
+
+    #[test]
+    fn echo_replied_before_recv_returns() {
+        let mut harness = Harness::connect(ServerConfig::default());
+
+        harness.write("< echo a >");
+        let sockets = &mut harness.sockets;
+        let cmd = harness.server.recv_at(sockets, harness.now).unwrap();
+        assert!(matches!(cmd, Some(Command::Echo(_))));
+        assert_eq!(harness.read(), "< echo a 0 >");
+
+        // without a clock there is no elapsed time
+        harness.write("< echo b >");
+        harness.server.recv(&mut harness.sockets).unwrap();
+        assert_eq!(harness.read(), "< echo b >");
+    }
+
+    #[test]
+    fn echo_software_version() {
+        let config = ServerConfig::default()
+            .with_version_string("1.2.0")
+            .unwrap();
+        let mut harness = Harness::connect(config);
+
+        harness.write("< echo >");
+        harness.recv();
+        assert_eq!(harness.read(), "< echo version=1.2.0 >");
+    }
+
+    /// Standard CAN frame as the application would pass it to the server.
+    fn frame(id: u16, data: &[u8]) -> socketcand::wire::Frame {
+        let id = embedded_can::StandardId::new(id).unwrap();
+        socketcand::wire::Frame::new(id, data).unwrap()
+    }
+
+    #[test]
+    fn isotp_message() {
+        let mut harness = Harness::connect(ServerConfig::default());
+
+        harness.write("< isotpmode >< isotpconf 7E0 7E8 normal 8 10 >");
+        harness.recv();
+        harness.recv();
+        assert_eq!(harness.read(), "< ok >< ok >");
+
+        // raw frames are not forwarded in ISO-TP mode
+        harness.send_frame(&frame(0x7E8, &[0x02, 0x01, 0x02]));
+        assert_eq!(harness.read(), "");
+
+        let sockets = &mut harness.sockets;
+        let data = [0x01, 0x02];
+        harness
+            .server
+            .send_isotp(sockets, harness.now, &data)
+            .unwrap();
+        assert_eq!(harness.read(), "< isotprecv 7E0 7E8 0102 >");
+        assert_eq!(harness.server.metrics().frames_sent, 1);
+    }
+
+    #[test]
+    fn txecho() {
+        let mut harness = Harness::connect(ServerConfig::default());
+
+        harness.write("< subscribe_tx 123 >");
+        harness.recv();
+        assert_eq!(harness.read(), "< ok >");
+
+        harness.send_frame(&frame(0x123, &[0xAB, 0xCD]));
+        harness.send_frame(&frame(0x124, &[0xAB, 0xCD]));
+        assert_eq!(harness.read(), "< txecho 123 2 AB CD >");
+    }
+
+    #[test]
+    fn send_batch() {
+        let mut harness = Harness::connect(ServerConfig::default());
+
+        harness.write("< rawmode >");
+        harness.recv();
+        assert_eq!(harness.read(), "< ok >");
+
+        // more frames than fit in one batch
+        let frame = frame(0x123, &[0; 8]);
+        let frames = [&frame; 32];
+        let sent = harness
+            .server
+            .send_batch(&mut harness.sockets, harness.now, &frames)
+            .unwrap();
+
+        assert!(sent > 0 && sent < frames.len());
+        assert_eq!(harness.read().matches("< frame 123 ").count(), sent);
+        assert_eq!(harness.server.metrics().frames_sent, sent as u64);
+        assert_eq!(harness.server.metrics().frames_dropped, 0);
+    }
+
+    #[test]
+    fn send_batch_with_txecho() {
+        let mut harness = Harness::connect(ServerConfig::default());
+
+        harness.write("< subscribe_tx 123 >");
+        harness.recv();
+        assert_eq!(harness.read(), "< ok >");
+
+        // frames are only forwarded in raw mode
+        let frames = [frame(0x123, &[0x01]), frame(0x124, &[0x02])];
+        let frames: vec::Vec<_> = frames.iter().collect();
+        let sent = harness
+            .server
+            .send_batch(&mut harness.sockets, harness.now, &frames)
+            .unwrap();
+
+        assert_eq!(sent, 0);
+        assert_eq!(harness.read(), "< txecho 123 1 01 >");
+    }
+
+    #[test]
+    fn add_count_job_deleted() {
+        let mut harness = Harness::connect(ServerConfig::default());
+
+        harness.write("< add_count 2 0 100000 123 1 AA >");
+        assert!(matches!(harness.recv(), Some(Command::AddCount(_))));
+
+        let id = frame(0x123, &[]).id;
+        assert!(harness.server.has_job(id));
+
+        harness.server.job_transmitted(id);
+        assert!(harness.server.has_job(id));
+
+        harness.server.job_transmitted(id);
+        assert!(!harness.server.has_job(id));
+    }
+
+    #[test]
+    fn auth() {
+        let config = ServerConfig::default().with_auth_token("secret").unwrap();
+        let mut harness = Harness::connect(config);
+
+        harness.write("< echo >");
+        harness.recv();
+        assert_eq!(harness.read(), "< error not_authenticated >");
+
+        harness.write("< auth wrong >");
+        harness.recv();
+        assert_eq!(harness.read(), "< error auth_failed >");
+
+        harness.write("< auth secret >");
+        harness.recv();
+        assert_eq!(harness.read(), "< ok >");
+
+        harness.write("< echo >");
+        assert!(matches!(harness.recv(), Some(Command::Echo(_))));
+        assert_eq!(harness.read(), "< echo >");
+    }
+
+    #[test]
+    fn request_frames() {
+        let mut harness = Harness::connect(ServerConfig::default());
+
+        let timestamp =
+            Duration::from_micros(harness.now.total_micros() as u64);
+        harness.send_frame(&frame(0x123, &[0xAA]));
+
+        harness.write("< request_frames 123 >< request_frames 124 >");
+        harness.recv();
+        harness.recv();
+
+        let last = socketcand::wire::Frame {
+            timestamp,
+            ..frame(0x123, &[0xAA])
+        };
+        assert_eq!(
+            harness.read(),
+            format!("{}< nack reason=\"not_found\" >", last)
+        );
+    }
+
+    #[test]
+    fn listen_failure_retried() {
+        let storage = Box::leak(Box::new([SocketStorage::EMPTY; 1]));
+        let mut sockets = SocketSet::new(&mut storage[..]);
+        // port 0 cannot be listened on
+        let config = ServerConfig::new(Port(0));
+        let mut server =
+            Server::new_with_config(&mut sockets, tcp_socket(), config);
+
+        for _ in 0..3 {
+            assert_eq!(server.recv_at(&mut sockets, Instant::ZERO), Ok(None));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "failed to listen on port 0")]
+    fn listen_retries_exhausted() {
+        let storage = Box::leak(Box::new([SocketStorage::EMPTY; 1]));
+        let mut sockets = SocketSet::new(&mut storage[..]);
+        let config = ServerConfig::new(Port(0)).retry_listen(2, 0);
+        let mut server =
+            Server::new_with_config(&mut sockets, tcp_socket(), config);
+
+        for _ in 0..2 {
+            server.recv_at(&mut sockets, Instant::ZERO).ok();
+        }
+    }
+
+    #[test]
+    fn aborted_connection_state_reset() {
+        let config = ServerConfig::default().with_auth_token("secret").unwrap();
+        let mut harness = Harness::connect(config);
+
+        harness.write("< auth secret >");
+        harness.recv();
+        assert_eq!(harness.read(), "< ok >");
+
+        // the client resets the connection instead of closing it
+        harness.sockets.get_mut::<Socket>(harness.client).abort();
+        harness.poll();
+        harness.recv();
+
+        harness.connect_client(49153);
+        harness.write("< echo >");
+        harness.recv();
+        assert_eq!(harness.read(), "< error not_authenticated >");
+    }
+
+    #[test]
+    fn padded_command() {
+        let mut harness = Harness::connect(ServerConfig::default());
+
+        harness.write("< send  123   2  AB CD >< echo >");
+        let Some(Command::Send(send)) = harness.recv() else {
+            panic!("expected send");
+        };
+        assert_eq!(send.data, [0xAB, 0xCD]);
+
+        // the following command is still parsed
+        assert!(matches!(harness.recv(), Some(Command::Echo(_))));
+    }
 }
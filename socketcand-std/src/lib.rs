@@ -0,0 +1,30 @@
+//! Blocking socketcand server built on [`std::net`].
+//!
+//! ```rust,no_run
+//! use socketcand_std::{Port, Server};
+//!
+//! let server = Server::bind(("0.0.0.0", Port::default().0))?;
+//!
+//! loop {
+//!     let mut connection = server.accept()?;
+//!
+//!     while let Ok(cmd) = connection.recv() {
+//!         println!("{:?}", cmd);
+//!     }
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+mod server;
+
+pub use server::{Connection, Server};
+
+/// Socketcand TCP port.
+#[derive(Debug)]
+pub struct Port(pub u16);
+
+impl Default for Port {
+    fn default() -> Self {
+        Port(29536)
+    }
+}
@@ -0,0 +1,198 @@
+use embedded_can::Frame;
+use socketcand::{
+    wire::{command, format_frame, Command},
+    Mode,
+};
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    str::from_utf8,
+    time::SystemTime,
+};
+
+/// Blocking socketcand server.
+#[derive(Debug)]
+pub struct Server {
+    listener: TcpListener,
+}
+
+impl Server {
+    /// Creates a new socketcand server listening on the given address.
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+        })
+    }
+
+    /// Returns the local address the server is listening on.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Wait for a client to connect.
+    pub fn accept(&self) -> io::Result<Connection> {
+        let (mut stream, _) = self.listener.accept()?;
+
+        // welcome message to client
+        stream.write_all("< hi >".as_bytes())?;
+
+        Ok(Connection {
+            stream,
+            buffer: Vec::new(),
+            mode: None,
+        })
+    }
+}
+
+/// Connection to a single socketcand client.
+#[derive(Debug)]
+pub struct Connection {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    mode: Option<Mode>,
+}
+
+impl Connection {
+    /// Returns the address of the connected client.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    /// Receive the next command from the client.
+    ///
+    /// Blocks until a complete command has been received. Returns an error of
+    /// kind [`ErrorKind::UnexpectedEof`] once the client disconnects.
+    pub fn recv(&mut self) -> io::Result<Command> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|&b| b == b'>') {
+                let message: Vec<u8> = self.buffer.drain(..=end).collect();
+
+                let cmd = from_utf8(&message)
+                    .ok()
+                    .and_then(|ascii| command(ascii.trim_start()).ok())
+                    .map(|(_, cmd)| cmd)
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            ErrorKind::InvalidData,
+                            "malformed command",
+                        )
+                    })?;
+
+                self.handle(&cmd)?;
+
+                return Ok(cmd);
+            }
+
+            let mut chunk = [0; 256];
+            let read = self.stream.read(&mut chunk)?;
+
+            if read == 0 {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+
+    /// Update connection state from a received command.
+    fn handle(&mut self, cmd: &Command) -> io::Result<()> {
+        let mode = match cmd {
+            Command::Open(open) => open.mode.clone().unwrap_or(Mode::Broadcast),
+            Command::RawMode(_) => Mode::Raw,
+            Command::BroadcastMode(_) => Mode::Broadcast,
+            Command::ControlMode(_) => Mode::Control,
+            Command::IsoTpMode(_) => Mode::IsoTp,
+            _ => return Ok(()),
+        };
+
+        self.mode = Some(mode);
+        self.stream.write_all("< ok >".as_bytes())
+    }
+
+    /// Send a CAN frame, timestamped with the current system time.
+    ///
+    /// Frames are only forwarded to the client in raw mode.
+    pub fn send_frame(&mut self, frame: &impl Frame) -> io::Result<()> {
+        if self.mode != Some(Mode::Raw) {
+            return Ok(());
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let mut out = String::new();
+        format_frame(&mut out, frame.id(), timestamp, frame.data())
+            .map_err(|_| io::Error::from(ErrorKind::Other))?;
+
+        self.stream.write_all(out.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use socketcand::wire::{response, Response};
+    use std::thread;
+
+    #[test]
+    fn raw_mode_session() {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(b"< open can0 >< rawmode >").unwrap();
+
+            // < hi >< ok >< ok > followed by the frame
+            let mut received = String::new();
+            while !received.contains("< frame") || !received.ends_with('>') {
+                let mut chunk = [0; 128];
+                let read = stream.read(&mut chunk).unwrap();
+                received.push_str(from_utf8(&chunk[..read]).unwrap());
+            }
+            received
+        });
+
+        let mut connection = server.accept().unwrap();
+        assert!(matches!(connection.recv().unwrap(), Command::Open(_)));
+        assert!(matches!(connection.recv().unwrap(), Command::RawMode(_)));
+
+        let frame = socketcand::wire::Frame::new(
+            embedded_can::StandardId::new(0x123).unwrap(),
+            &[0x11, 0x22],
+        )
+        .unwrap();
+        connection.send_frame(&frame).unwrap();
+
+        let received = client.join().unwrap();
+        let (hello, frame_msg) =
+            received.split_at(received.find("< frame").unwrap());
+        assert_eq!(hello, "< hi >< ok >< ok >");
+
+        let (_, Response::Frame(received)) = response(frame_msg).unwrap()
+        else {
+            panic!("expected frame");
+        };
+        assert_eq!(received.id, frame.id);
+        assert_eq!(received.data, frame.data);
+    }
+
+    #[test]
+    fn open_with_mode() {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(b"< open can0 raw >").unwrap();
+            stream
+        });
+
+        let mut connection = server.accept().unwrap();
+        assert!(matches!(connection.recv().unwrap(), Command::Open(_)));
+        assert_eq!(connection.mode, Some(Mode::Raw));
+
+        client.join().unwrap();
+    }
+}
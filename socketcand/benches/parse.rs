@@ -0,0 +1,26 @@
+//! Compare the `nom` parsers with the hand-written ones in `wire::fast`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use socketcand::wire::{command, fast, response};
+
+const SEND: &str = "< send 12345678 8 01 02 03 04 05 06 07 08 >";
+const FRAME: &str = "< frame 123 1700000000.123456 0102030405060708 >";
+
+fn send(c: &mut Criterion) {
+    let mut group = c.benchmark_group("send");
+    group.bench_function("nom", |b| b.iter(|| command(black_box(SEND))));
+    group.bench_function("fast", |b| b.iter(|| fast::command(black_box(SEND))));
+    group.finish();
+}
+
+fn frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    group.bench_function("nom", |b| b.iter(|| response(black_box(FRAME))));
+    group.bench_function("fast", |b| {
+        b.iter(|| fast::response(black_box(FRAME)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, send, frame);
+criterion_main!(benches);
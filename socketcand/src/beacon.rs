@@ -1,34 +1,276 @@
 //! Service discovery beacon.
+//!
+//! Servers announce themselves by periodically sending a UDP datagram to
+//! [`PORT`], either as an IPv4 broadcast or to one of the multicast groups
+//! below. Each datagram carries a single XML document, without a trailing
+//! newline:
+//!
+//! ```text
+//! <CANBeacon name="NAME" type="TYPE" description="DESCRIPTION">
+//!     <URL>can://ADDRESS:PORT</URL>
+//!     <Bus name="can0"/>
+//! </CANBeacon>
+//! ```
+//!
+//! The `description` attribute is optional. There is one tab-indented `<URL>`
+//! per address the server listens on and one `<Bus>` per available bus. Line
+//! breaks are shown for readability only; see [`format`].
 
 use crate::Bus;
-use core::fmt::Formatter;
+use core::{
+    fmt::{Display, Formatter, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
 
 /// Port used for broadcasting service discovery datagrams.
 pub const PORT: u16 = 42000;
 
+/// IPv4 multicast group for service discovery datagrams.
+pub const BEACON_MULTICAST_V4: Ipv4Addr = Ipv4Addr::new(239, 255, 42, 0);
+
+/// IPv6 multicast group for service discovery datagrams (link-local
+/// all-nodes, `ff02::1`).
+pub const BEACON_MULTICAST_V6: Ipv6Addr =
+    Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+
+/// Maximum beacon description length in characters.
+pub const MAX_DESCRIPTION_LEN: usize = 255;
+
+/// Beacon formatting error.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum BeaconError {
+    /// The description is longer than [`MAX_DESCRIPTION_LEN`] characters.
+    DescriptionTooLong,
+    /// Writing to the formatter failed.
+    Fmt,
+}
+
+impl From<core::fmt::Error> for BeaconError {
+    fn from(_: core::fmt::Error) -> Self {
+        BeaconError::Fmt
+    }
+}
+
+/// Write `s` escaped for use in an XML attribute value.
+fn xml_escape(s: &str, w: &mut impl Write) -> core::fmt::Result {
+    for c in s.chars() {
+        match c {
+            '<' => w.write_str("&lt;")?,
+            '>' => w.write_str("&gt;")?,
+            '&' => w.write_str("&amp;")?,
+            '"' => w.write_str("&quot;")?,
+            '\'' => w.write_str("&apos;")?,
+            c => w.write_char(c)?,
+        }
+    }
+
+    Ok(())
+}
+
 /// Format a beacon message.
+///
+/// One `<URL>` tag is written for each address the server is reachable on.
+/// Nothing is written if the description is longer than
+/// [`MAX_DESCRIPTION_LEN`] characters.
 pub fn format(
     fmt: &mut Formatter<'_>,
     name: &str,
     device_kind: &str,
     description: Option<&str>,
-    ip: core::net::IpAddr,
+    addresses: &[IpAddr],
     port: u16,
     busses: &[Bus],
-) -> core::fmt::Result {
-    write!(fmt, r#"<CANBeacon name="{}" type="{}""#, name, device_kind)?;
+) -> Result<(), BeaconError> {
+    if description.is_some_and(|description| {
+        description.chars().count() > MAX_DESCRIPTION_LEN
+    }) {
+        return Err(BeaconError::DescriptionTooLong);
+    }
+
+    write!(fmt, r#"<CANBeacon name=""#)?;
+    xml_escape(name, fmt)?;
+    write!(fmt, r#"" type=""#)?;
+    xml_escape(device_kind, fmt)?;
+    write!(fmt, r#"""#)?;
 
     if let Some(description) = description {
-        write!(fmt, r#" description="{}""#, description)?;
+        write!(fmt, r#" description=""#)?;
+        xml_escape(description, fmt)?;
+        write!(fmt, r#"""#)?;
     }
 
     write!(fmt, ">")?;
 
-    write!(fmt, "\t<URL>can://{}:{}</URL>", ip, port)?;
+    for ip in addresses {
+        match ip {
+            IpAddr::V4(ip) => {
+                write!(fmt, "\t<URL>can://{}:{}</URL>", ip, port)?
+            }
+            IpAddr::V6(ip) => {
+                write!(fmt, "\t<URL>can://[{}]:{}</URL>", ip, port)?
+            }
+        }
+    }
 
     for bus in busses {
-        write!(fmt, r#"\t<Bus name="{}">"#, bus)?;
+        write!(fmt, "\t<Bus name=\"{}\"/>", bus)?;
     }
 
-    write!(fmt, "</CANBeacon>")
+    write!(fmt, "</CANBeacon>")?;
+
+    Ok(())
+}
+
+/// Service discovery beacon message.
+///
+/// # Example
+/// ```rust
+/// use core::net::{IpAddr, Ipv4Addr};
+/// use socketcand::{beacon::BeaconMessage, Bus};
+///
+/// let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10));
+/// let busses = [Bus::new(0)];
+/// let beacon = BeaconMessage::new("gateway", "SocketCAN", &ip, 29536, &busses);
+///
+/// let message = beacon.to_string();
+/// assert!(message.contains("<URL>can://192.168.1.10:29536</URL>"));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct BeaconMessage<'a> {
+    /// Server name.
+    pub name: &'a str,
+    /// Device type (e.g. `SocketCAN`).
+    pub device_kind: &'a str,
+    /// Optional description.
+    pub description: Option<&'a str>,
+    /// Addresses the server is reachable on.
+    pub addresses: &'a [IpAddr],
+    /// Server TCP port.
+    pub port: u16,
+    /// Busses provided by the server.
+    pub busses: &'a [Bus],
+}
+
+impl<'a> BeaconMessage<'a> {
+    /// Create a beacon for a server reachable on a single address.
+    pub fn new(
+        name: &'a str,
+        device_kind: &'a str,
+        ip: &'a IpAddr,
+        port: u16,
+        busses: &'a [Bus],
+    ) -> Self {
+        Self::with_addresses(
+            name,
+            device_kind,
+            core::slice::from_ref(ip),
+            port,
+            busses,
+        )
+    }
+
+    /// Create a beacon for a server reachable on several addresses.
+    pub fn with_addresses(
+        name: &'a str,
+        device_kind: &'a str,
+        addresses: &'a [IpAddr],
+        port: u16,
+        busses: &'a [Bus],
+    ) -> Self {
+        Self {
+            name,
+            device_kind,
+            description: None,
+            addresses,
+            port,
+            busses,
+        }
+    }
+
+    /// Set the description.
+    ///
+    /// Formatting the beacon fails if the description is longer than
+    /// [`MAX_DESCRIPTION_LEN`] characters.
+    pub fn with_description(mut self, description: &'a str) -> Self {
+        self.description = Some(description);
+        self
+    }
+}
+
+impl Display for BeaconMessage<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        format(
+            f,
+            self.name,
+            self.device_kind,
+            self.description,
+            self.addresses,
+            self.port,
+            self.busses,
+        )
+        .map_err(|_| core::fmt::Error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_per_address() {
+        let addresses = [
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ];
+        let beacon = BeaconMessage::with_addresses(
+            "gw",
+            "SocketCAN",
+            &addresses,
+            29536,
+            &[],
+        );
+        let message = beacon.to_string();
+
+        assert!(message.contains("<URL>can://10.0.0.1:29536</URL>"));
+        assert!(message.contains("<URL>can://[::1]:29536</URL>"));
+    }
+
+    #[test]
+    fn escapes_attributes() {
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let busses = [Bus::new(0)];
+        let beacon =
+            BeaconMessage::new("a<b", "SocketCAN", &ip, 29536, &busses)
+                .with_description(r#"Tom & Jerry's "gateway""#);
+
+        assert_eq!(
+            beacon.to_string(),
+            "<CANBeacon name=\"a&lt;b\" type=\"SocketCAN\" \
+             description=\"Tom &amp; Jerry&apos;s &quot;gateway&quot;\">\
+             \t<URL>can://10.0.0.1:29536</URL>\
+             \t<Bus name=\"can0\"/>\
+             </CANBeacon>"
+        );
+        // formatting is repeatable
+        assert_eq!(beacon.to_string(), beacon.to_string());
+    }
+
+    #[test]
+    fn description_too_long() {
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let description = "x".repeat(MAX_DESCRIPTION_LEN);
+        let beacon = BeaconMessage::new("gw", "SocketCAN", &ip, 29536, &[])
+            .with_description(&description);
+
+        let mut out = std::string::String::new();
+        assert!(write!(&mut out, "{}", beacon).is_ok());
+
+        let description = "x".repeat(MAX_DESCRIPTION_LEN + 1);
+        let beacon = beacon.with_description(&description);
+
+        out.clear();
+        assert!(write!(&mut out, "{}", beacon).is_err());
+        assert!(out.is_empty());
+    }
 }
@@ -0,0 +1,448 @@
+//! CAN network busses.
+
+use crate::wire::Open;
+use core::{
+    fmt::{Display, Write},
+    str::from_utf8,
+};
+use heapless::{FnvIndexSet, String};
+
+/// Largest supported bus index.
+pub const MAX_BUS_INDEX: u16 = u16::MAX;
+
+/// Length of the longest bus name, `vcan65535`.
+pub const MAX_BUS_NAME_LEN: usize = "vcan".len() + 5;
+
+/// Type of CAN network interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum BusType {
+    /// Physical interface (e.g. `can0`).
+    Physical,
+    /// Virtual interface (e.g. `vcan0`).
+    Virtual,
+}
+
+impl BusType {
+    /// Returns the interface name prefix, `can` or `vcan`.
+    pub const fn prefix(&self) -> &'static str {
+        match self {
+            BusType::Physical => "can",
+            BusType::Virtual => "vcan",
+        }
+    }
+}
+
+/// CAN network bus.
+///
+/// Rather than allowing arbitrary bus names and having to store strings, bus
+/// names must be of the form `vcanN` or `canN` where `N` is a positive
+/// integer.
+///
+/// # Example
+/// ```rust
+/// use socketcand::Bus;
+///
+/// let bus = Bus::new(0); // same as `can0`
+/// let bus = Bus::new_virtual(7); // same as `vcan7`
+///
+/// // constructors are `const` for use in static initializers
+/// static BUSSES: [Bus; 2] = [Bus::new(0), Bus::new(1)];
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bus {
+    index: u16,
+    bus_type: BusType,
+}
+
+impl Bus {
+    /// Create a new [`Bus`] instance.
+    pub const fn new(index: u16) -> Self {
+        Self {
+            index,
+            bus_type: BusType::Physical,
+        }
+    }
+
+    /// Create a new virtual [`Bus`] instance.
+    pub const fn new_virtual(index: u16) -> Self {
+        Self {
+            index,
+            bus_type: BusType::Virtual,
+        }
+    }
+
+    /// Returns the bus index number.
+    pub const fn index(&self) -> u16 {
+        self.index
+    }
+
+    /// Returns the bus index number if it fits in a `u8`, for interfaces
+    /// that only support indices up to 255.
+    pub const fn try_index_as_u8(&self) -> Option<u8> {
+        if self.index <= u8::MAX as u16 {
+            Some(self.index as u8)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the bus type.
+    pub const fn bus_type(&self) -> BusType {
+        self.bus_type
+    }
+
+    /// Returns if the bus is a virtual bus.
+    pub const fn is_virtual(&self) -> bool {
+        matches!(self.bus_type, BusType::Virtual)
+    }
+
+    /// Returns a wrapper that displays the bus name in uppercase (e.g.
+    /// `CAN0`), as used by some Windows CAN tools.
+    pub const fn display_upper(&self) -> UpperBusDisplay {
+        UpperBusDisplay(*self)
+    }
+
+    /// Parse a bus name such as `can0` or `VCAN7`, ignoring case.
+    pub fn from_str_case_insensitive(name: &str) -> Option<Bus> {
+        let split = name.find(|c: char| c.is_ascii_digit())?;
+        let (prefix, index) = name.split_at(split);
+        let index = index.parse().ok()?;
+
+        if prefix.eq_ignore_ascii_case("vcan") {
+            Some(Bus::new_virtual(index))
+        } else if prefix.eq_ignore_ascii_case("can") {
+            Some(Bus::new(index))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the bus name (e.g. `can0`) as a fixed capacity string.
+    pub fn as_heapless_string(&self) -> String<MAX_BUS_NAME_LEN> {
+        let mut name = String::new();
+        // the buffer fits the name of any bus index
+        write!(&mut name, "{}", self).ok();
+        name
+    }
+
+    /// Returns the interface name with a trailing null byte (e.g. `can0\0`),
+    /// for use with native SocketCAN APIs.
+    ///
+    /// The bytes can be passed to `libc::if_nametoindex` or converted with
+    /// [`CStr::from_bytes_until_nul`](core::ffi::CStr::from_bytes_until_nul).
+    ///
+    /// # Example
+    /// ```rust
+    /// use core::ffi::CStr;
+    /// use socketcand::Bus;
+    ///
+    /// let name = Bus::new_virtual(3).socket_name();
+    /// let name = CStr::from_bytes_until_nul(name.as_bytes()).unwrap();
+    ///
+    /// assert_eq!(name.to_str(), Ok("vcan3"));
+    /// ```
+    pub fn socket_name(&self) -> String<16> {
+        let name = BusNameBuffer::from(*self);
+        // bus names are ASCII, and the longest one and its terminator fit
+        // in `IFNAMSIZ` bytes
+        let name = from_utf8(name.to_bytes_with_nul()).unwrap_or_default();
+        String::try_from(name).unwrap_or_default()
+    }
+}
+
+/// Every [`Open`] index fits in a [`Bus`], so this conversion is infallible;
+/// `TryFrom<Open>` is also available through the standard blanket impl.
+impl From<Open> for Bus {
+    fn from(open: Open) -> Self {
+        Self {
+            index: open.index,
+            bus_type: open.bus_type,
+        }
+    }
+}
+
+impl From<Bus> for Open {
+    fn from(bus: Bus) -> Self {
+        Self {
+            index: bus.index,
+            bus_type: bus.bus_type,
+            mode: None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Bus {
+    /// Returns the CAN interfaces listed in `/proc/net/dev`.
+    ///
+    /// Interfaces not named `canN` or `vcanN` are ignored.
+    pub fn available() -> std::io::Result<std::vec::Vec<Bus>> {
+        let dev = std::fs::read_to_string("/proc/net/dev")?;
+        Ok(parse_net_dev(&dev))
+    }
+}
+
+/// Parse the CAN interfaces out of the contents of `/proc/net/dev`.
+#[cfg(any(test, feature = "std"))]
+fn parse_net_dev(dev: &str) -> std::vec::Vec<Bus> {
+    dev.lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter_map(|(name, _)| {
+            let name = name.trim();
+
+            if let Some(index) = name.strip_prefix("vcan") {
+                index.parse().ok().map(Bus::new_virtual)
+            } else if let Some(index) = name.strip_prefix("can") {
+                index.parse().ok().map(Bus::new)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+impl Display for Bus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}{}", self.bus_type.prefix(), self.index)
+    }
+}
+
+/// Uppercase [`Bus`] name display, created with [`Bus::display_upper`].
+#[derive(Debug, Clone, Copy)]
+pub struct UpperBusDisplay(Bus);
+
+impl Display for UpperBusDisplay {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let prefix = match self.0.bus_type {
+            BusType::Physical => "CAN",
+            BusType::Virtual => "VCAN",
+        };
+
+        write!(f, "{}{}", prefix, self.0.index)
+    }
+}
+
+#[cfg(feature = "defmt-03")]
+impl defmt::Format for Bus {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "{=str}{}", self.bus_type.prefix(), self.index)
+    }
+}
+
+/// Null-terminated bus name stored on the stack, e.g. for C FFI.
+///
+/// # Example
+/// ```rust
+/// use socketcand::{bus::BusNameBuffer, Bus};
+///
+/// let name = BusNameBuffer::from(Bus::new_virtual(3));
+///
+/// assert_eq!(name.as_bytes(), b"vcan3");
+/// assert_eq!(name.to_bytes_with_nul(), b"vcan3\0");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusNameBuffer([u8; 16]);
+
+impl BusNameBuffer {
+    /// Returns the bus name without the null terminator.
+    pub fn as_bytes(&self) -> &[u8] {
+        let len = self.0.iter().position(|&b| b == 0).unwrap_or(self.0.len());
+        &self.0[..len]
+    }
+}
+
+impl From<Bus> for BusNameBuffer {
+    fn from(bus: Bus) -> Self {
+        let name = bus.as_heapless_string();
+        let mut buf = [0; 16];
+
+        // names are at most `MAX_BUS_NAME_LEN` bytes, leaving room for the
+        // null terminator
+        buf[..name.len()].copy_from_slice(name.as_bytes());
+
+        Self(buf)
+    }
+}
+
+impl core::ops::Deref for BusNameBuffer {
+    type Target = core::ffi::CStr;
+
+    fn deref(&self) -> &Self::Target {
+        core::ffi::CStr::from_bytes_until_nul(&self.0).unwrap_or_default()
+    }
+}
+
+/// Set of open busses.
+///
+/// `N` is the maximum number of busses and must be a power of two.
+///
+/// # Example
+/// ```rust
+/// use socketcand::{Bus, BusSet};
+///
+/// let mut busses = BusSet::<4>::new();
+/// busses.open(Bus::new(0)).unwrap();
+/// busses.open(Bus::new(1)).unwrap();
+///
+/// assert!(busses.is_open(Bus::new(1)));
+/// busses.close(Bus::new(1));
+/// assert!(!busses.is_open(Bus::new(1)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BusSet<const N: usize> {
+    busses: FnvIndexSet<Bus, N>,
+}
+
+impl<const N: usize> BusSet<N> {
+    /// Create an empty [`BusSet`].
+    pub fn new() -> Self {
+        Self {
+            busses: FnvIndexSet::new(),
+        }
+    }
+
+    /// Mark a bus as open.
+    ///
+    /// Returns `Ok(true)` if the bus was not already open, or `Err(bus)` if the
+    /// set is full.
+    pub fn open(&mut self, bus: Bus) -> Result<bool, Bus> {
+        self.busses.insert(bus)
+    }
+
+    /// Mark a bus as closed.
+    ///
+    /// Returns `true` if the bus was open.
+    pub fn close(&mut self, bus: Bus) -> bool {
+        self.busses.remove(&bus)
+    }
+
+    /// Returns if the bus is open.
+    pub fn is_open(&self, bus: Bus) -> bool {
+        self.busses.contains(&bus)
+    }
+
+    /// Returns an iterator over the open busses.
+    pub fn iter(&self) -> impl Iterator<Item = &Bus> {
+        self.busses.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_without_alloc() {
+        let mut out = String::<32>::new();
+        write!(&mut out, "{}", Bus::new(0)).unwrap();
+        assert_eq!(out, "can0");
+
+        out.clear();
+        write!(&mut out, "{}", Bus::new_virtual(7)).unwrap();
+        assert_eq!(out, "vcan7");
+
+        // as written by `beacon::format`
+        out.clear();
+        write!(&mut out, "\t<Bus name=\"{}\"/>", Bus::new(1)).unwrap();
+        assert_eq!(out, "\t<Bus name=\"can1\"/>");
+    }
+
+    #[test]
+    fn display_large_index() {
+        assert_eq!(Bus::new(999).to_string(), "can999");
+        assert_eq!(Bus::new_virtual(1000).to_string(), "vcan1000");
+        assert_eq!(Bus::new(MAX_BUS_INDEX).to_string(), "can65535");
+    }
+
+    #[test]
+    fn heapless_string_fits_max_index() {
+        let name = Bus::new_virtual(MAX_BUS_INDEX).as_heapless_string();
+
+        assert_eq!(name, "vcan65535");
+        assert_eq!(name.len(), MAX_BUS_NAME_LEN);
+        assert_eq!(Bus::new(999).as_heapless_string(), "can999");
+    }
+
+    #[test]
+    fn open_conversion() {
+        let open = Open {
+            index: 1000,
+            bus_type: BusType::Virtual,
+            mode: None,
+        };
+        let bus = Bus::from(open.clone());
+
+        assert_eq!(bus, Bus::new_virtual(1000));
+        assert_eq!(Open::from(bus), open);
+    }
+
+    #[test]
+    fn uppercase_names() {
+        assert_eq!(Bus::new(0).display_upper().to_string(), "CAN0");
+        assert_eq!(Bus::new_virtual(7).display_upper().to_string(), "VCAN7");
+
+        for name in ["vcan7", "VCAN7", "VCan7"] {
+            assert_eq!(
+                Bus::from_str_case_insensitive(name),
+                Some(Bus::new_virtual(7))
+            );
+        }
+        assert_eq!(Bus::from_str_case_insensitive("CAN0"), Some(Bus::new(0)));
+        assert_eq!(Bus::from_str_case_insensitive("slcan0"), None);
+        assert_eq!(Bus::from_str_case_insensitive("can"), None);
+        assert_eq!(Bus::from_str_case_insensitive("can0x"), None);
+    }
+
+    #[test]
+    fn name_buffer_fits_max_index() {
+        let name = BusNameBuffer::from(Bus::new_virtual(MAX_BUS_INDEX));
+
+        assert_eq!(name.as_bytes(), b"vcan65535");
+        assert_eq!(name.to_str(), Ok("vcan65535"));
+    }
+
+    #[test]
+    fn bus_set_full() {
+        let mut busses = BusSet::<2>::new();
+        assert_eq!(busses.open(Bus::new(0)), Ok(true));
+        assert_eq!(busses.open(Bus::new(0)), Ok(false));
+        assert_eq!(busses.open(Bus::new_virtual(0)), Ok(true));
+        assert_eq!(busses.open(Bus::new(1)), Err(Bus::new(1)));
+        assert_eq!(busses.iter().count(), 2);
+    }
+
+    #[test]
+    fn parse_net_dev_busses() {
+        let dev = "\
+Inter-|   Receive                            |  Transmit
+ face |bytes    packets errs drop fifo frame |bytes    packets errs drop
+    lo:     100       1    0    0    0     0 |     100       1    0    0
+  can0:       0       0    0    0    0     0 |       0       0    0    0
+ vcan7:       0       0    0    0    0     0 |       0       0    0    0
+  eth0:       0       0    0    0    0     0 |       0       0    0    0
+";
+        let busses = parse_net_dev(dev);
+
+        assert_eq!(busses.len(), 2);
+        assert_eq!(busses[0].index(), 0);
+        assert!(!busses[0].is_virtual());
+        assert_eq!(busses[1].index(), 7);
+        assert!(busses[1].is_virtual());
+    }
+
+    #[test]
+    fn index_as_u8() {
+        assert_eq!(Bus::new(255).try_index_as_u8(), Some(255));
+        assert_eq!(Bus::new_virtual(256).try_index_as_u8(), None);
+    }
+
+    #[test]
+    fn socket_name_is_null_terminated() {
+        assert_eq!(Bus::new(0).socket_name(), "can0\0");
+        assert_eq!(
+            Bus::new_virtual(MAX_BUS_INDEX).socket_name(),
+            "vcan65535\0"
+        );
+    }
+}
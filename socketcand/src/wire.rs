@@ -1,25 +1,256 @@
 //! Wire protocol parsing.
+//!
+//! The parsers never panic, whatever the input: malformed input is reported
+//! as a [`ParseError`]. Outside of tests this module denies the
+//! `clippy::panic`, `clippy::unwrap_used`, `clippy::expect_used`,
+//! `clippy::unimplemented` and `clippy::indexing_slicing` lints, and the
+//! parsers are fuzzed through the entry points enabled by the `fuzz` feature.
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::panic,
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::unimplemented,
+        clippy::indexing_slicing
+    )
+)]
+
+use crate::bus::BusType;
+use crate::Mode;
+use core::fmt::{Display, Formatter, Write};
+use core::marker::PhantomData;
+use core::net::IpAddr;
 use core::str::FromStr;
 use core::time::Duration;
 use embedded_can::{ExtendedId, Id, StandardId};
-use heapless::Vec;
+use heapless::{FnvIndexMap, IndexMapValues, String, Vec};
 use nom::{
     branch::alt,
-    bytes::{complete::tag, streaming::take_while},
+    bytes::{
+        complete::tag,
+        streaming::{take_till1, take_while},
+    },
     character::{
-        complete::char,
+        complete::{char, line_ending, multispace0},
         streaming::{digit1, hex_digit1},
     },
-    combinator::{map, map_res, peek},
-    sequence::{delimited, terminated, tuple},
-    IResult,
+    combinator::{cut, map, map_res, opt, peek, verify},
+    error::{context, ContextError, ErrorKind, FromExternalError},
+    sequence::{delimited, preceded, terminated, tuple},
+    Parser,
 };
 
-/// Maximum frame data length.
-const MAX_FRAME_DATA_LEN: usize = 8;
+pub mod ascii;
+#[cfg(feature = "bytes")]
+mod codec;
+pub mod fast;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+#[cfg(feature = "arbitrary")]
+pub mod generator;
+pub mod util;
+#[cfg(feature = "winnow")]
+pub mod winnow;
+
+#[cfg(feature = "bytes")]
+pub use codec::Codec;
+
+use util::parse_hex_bytes;
+
+/// Maximum classic CAN frame data length.
+pub const MAX_DATA_LEN: usize = 8;
+
+/// Maximum CAN FD frame data length.
+pub const MAX_FD_DATA_LEN: usize = 64;
+
+/// Maximum CAN XL payload length.
+///
+/// The full 2048 bytes are only supported with the `std` feature; embedded
+/// targets are limited to 64 bytes.
+pub const MAX_XL_DATA_LEN: usize =
+    if cfg!(feature = "std") { 2048 } else { 64 };
+
+/// CAN XL payload.
+///
+/// Boxed with the `std` feature, so the full 2048 byte payload does not make
+/// every [`Command`] and [`Response`] over 2 KiB.
+#[cfg(feature = "std")]
+pub type XlData = std::boxed::Box<Vec<u8, MAX_XL_DATA_LEN>>;
+
+/// CAN XL payload.
+#[cfg(not(feature = "std"))]
+pub type XlData = Vec<u8, MAX_XL_DATA_LEN>;
+
+/// Wrap parsed payload bytes in an [`XlData`].
+fn xl_data(data: Vec<u8, MAX_XL_DATA_LEN>) -> XlData {
+    #[cfg(feature = "std")]
+    return std::boxed::Box::new(data);
+    #[cfg(not(feature = "std"))]
+    return data;
+}
+
+/// Maximum network interface name length, as `IFNAMSIZ` on Linux.
+///
+/// Longer than [`bus::MAX_BUS_NAME_LEN`](crate::bus::MAX_BUS_NAME_LEN), as
+/// `< controlmode >` accepts any interface name rather than only a
+/// [`Bus`](crate::Bus).
+const MAX_INTERFACE_NAME_LEN: usize = 16;
+
+/// Maximum echo nonce length.
+const MAX_ECHO_NONCE_LEN: usize = 32;
+
+/// Maximum error reason length.
+const MAX_ERROR_REASON_LEN: usize = 32;
+
+/// Maximum authentication token length.
+const MAX_AUTH_TOKEN_LEN: usize = 64;
+
+/// Length of the input snippet shown when formatting a [`ParseError`].
+const ERROR_SNIPPET_LEN: usize = 32;
+
+/// Parse error.
+#[derive(PartialEq, Clone)]
+pub struct ParseError<'a> {
+    /// Remaining input at the point of failure.
+    pub input: &'a str,
+    /// Kind of parser that failed.
+    pub kind: ErrorKind,
+    /// Description of the failure, if known (e.g. `"malformed send command"`).
+    pub context: Option<&'static str>,
+}
+
+impl<'a> ParseError<'a> {
+    fn new(input: &'a str, kind: ErrorKind) -> Self {
+        Self {
+            input,
+            kind,
+            context: None,
+        }
+    }
+
+    /// Returns up to the first 32 bytes of the input that failed to parse.
+    pub fn snippet(&self) -> &'a str {
+        let mut end = self.input.len().min(ERROR_SNIPPET_LEN);
+
+        while !self.input.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        &self.input[..end]
+    }
+}
+
+impl core::fmt::Debug for ParseError<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ParseError")
+            .field("input", &self.snippet())
+            .field("kind", &self.kind)
+            .field("context", &self.context)
+            .finish()
+    }
+}
+
+impl Display for ParseError<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self.context {
+            Some(context) => write!(f, "{}", context)?,
+            None => write!(f, "parse error ({:?})", self.kind)?,
+        }
+
+        write!(f, " at {:?}", self.snippet())
+    }
+}
+
+impl<'a> nom::error::ParseError<&'a str> for ParseError<'a> {
+    fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+        Self::new(input, kind)
+    }
+
+    fn append(_input: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl<'a> ContextError<&'a str> for ParseError<'a> {
+    fn add_context(
+        _input: &'a str,
+        ctx: &'static str,
+        mut other: Self,
+    ) -> Self {
+        // keep the innermost, most specific, context
+        other.context.get_or_insert(ctx);
+        other
+    }
+}
+
+impl<'a, E> FromExternalError<&'a str, E> for ParseError<'a> {
+    fn from_external_error(input: &'a str, kind: ErrorKind, _e: E) -> Self {
+        Self::new(input, kind)
+    }
+}
+
+/// Result type returned by the parsers.
+pub type IResult<'a, O> = nom::IResult<&'a str, O, ParseError<'a>>;
+
+/// Parse a command keyword followed by its arguments.
+///
+/// Once the keyword has matched the parser does not backtrack, so malformed
+/// arguments fail with `description` rather than trying the remaining
+/// alternatives.
+fn keyword<'a, O, F>(
+    keyword: &'static str,
+    description: &'static str,
+    arguments: F,
+) -> impl FnMut(&'a str) -> IResult<'a, O>
+where
+    F: Parser<&'a str, O, ParseError<'a>>,
+{
+    preceded(tag(keyword), cut(context(description, arguments)))
+}
+
+/// Write a CAN identifier in its wire format.
+fn write_id(w: &mut impl Write, id: Id) -> core::fmt::Result {
+    match id {
+        Id::Standard(id) => write!(w, "{:03X}", id.as_raw()),
+        Id::Extended(id) => write!(w, "{:08X}", id.as_raw()),
+    }
+}
+
+/// Warn when `interval` has sub-microsecond components, which are truncated
+/// on the wire.
+#[cfg_attr(not(feature = "defmt-03"), allow(unused_variables))]
+fn warn_truncated(interval: Duration) {
+    #[cfg(feature = "defmt-03")]
+    if !interval.subsec_nanos().is_multiple_of(1000) {
+        defmt::warn!(
+            "Interval {=u32}ns is truncated to microseconds",
+            interval.subsec_nanos()
+        );
+    }
+}
+
+/// Combine seconds and microseconds into a [`Duration`].
+///
+/// Fails rather than overflowing on out of range values.
+#[inline]
+fn seconds_micros(
+    input: &str,
+    secs: u64,
+    micros: u64,
+) -> Result<Duration, nom::Err<ParseError<'_>>> {
+    Duration::from_secs(secs)
+        .checked_add(Duration::from_micros(micros))
+        .ok_or_else(|| {
+            let mut err = ParseError::new(input, ErrorKind::MapRes);
+            err.context = Some("duration out of range");
+            nom::Err::Failure(err)
+        })
+}
 
 /// Parse CAN id.
-fn id(input: &str) -> IResult<&str, Id> {
+#[inline]
+fn id(input: &str) -> IResult<'_, Id> {
     let (input, (extended, id)) = tuple((
         peek(map_res(hex_digit1, |id: &str| match id.len() {
             8 => Ok(true),
@@ -36,9 +267,9 @@ fn id(input: &str) -> IResult<&str, Id> {
         match ExtendedId::new(id) {
             Some(id) => Id::Extended(id),
             None => {
-                return Err(nom::Err::Error(nom::error::Error::new(
+                return Err(nom::Err::Error(ParseError::new(
                     input,
-                    nom::error::ErrorKind::MapRes,
+                    ErrorKind::MapRes,
                 )))
             }
         }
@@ -46,9 +277,9 @@ fn id(input: &str) -> IResult<&str, Id> {
         match StandardId::new(id as u16) {
             Some(id) => Id::Standard(id),
             None => {
-                return Err(nom::Err::Error(nom::error::Error::new(
+                return Err(nom::Err::Error(ParseError::new(
                     input,
-                    nom::error::ErrorKind::MapRes,
+                    ErrorKind::MapRes,
                 )))
             }
         }
@@ -58,50 +289,119 @@ fn id(input: &str) -> IResult<&str, Id> {
 }
 
 /// Open command.
+///
+/// Some implementations accept an initial mode after the bus name (e.g.
+/// `< open can0 raw >`), saving a separate mode command.
 #[derive(Debug, PartialEq, Clone)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 pub struct Open {
     /// Interface index.
-    pub index: u8,
-    /// Virtual interface (e.g. `vcan0`).
-    pub virt: bool,
+    pub index: u16,
+    /// Interface type (e.g. virtual for `vcan0`).
+    pub bus_type: BusType,
+    /// Mode to enter once the bus is open, broadcast mode if `None`.
+    pub mode: Option<Mode>,
 }
 
-fn open<'a>(input: &'a str) -> IResult<&'a str, Open> {
-    let (input, (interface_type, index)) = delimited(
-        tag("< open "),
-        tuple((
-            alt((tag("can"), tag("vcan"))),
-            map_res(digit1, u8::from_str),
-        )),
-        tag(" >"),
+#[inline]
+fn open(input: &str) -> IResult<'_, Open> {
+    let (input, (bus_type, index, mode)) = keyword(
+        "< open ",
+        "malformed open command",
+        terminated(
+            tuple((
+                alt((
+                    map(tag("can"), |_| BusType::Physical),
+                    map(tag("vcan"), |_| BusType::Virtual),
+                )),
+                context(
+                    "interface index must be decimal",
+                    map_res(digit1, u16::from_str),
+                ),
+                opt(preceded(
+                    char(' '),
+                    alt((
+                        map(tag("broadcast"), |_| Mode::Broadcast),
+                        map(tag("raw"), |_| Mode::Raw),
+                        map(tag("control"), |_| Mode::Control),
+                        map(tag("isotp"), |_| Mode::IsoTp),
+                    )),
+                )),
+            )),
+            tag(" >"),
+        ),
     )(input)?;
 
-    let virt = interface_type == "vcan";
+    Ok((
+        input,
+        Open {
+            index,
+            bus_type,
+            mode,
+        },
+    ))
+}
+
+impl Display for Open {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< open {}{}", self.bus_type.prefix(), self.index)?;
+
+        if let Some(mode) = &self.mode {
+            write!(f, " {}", mode.as_str())?;
+        }
 
-    Ok((input, Open { index, virt }))
+        write!(f, " >")
+    }
 }
 
 /// Frame job add command.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Add {
     /// Interval.
+    ///
+    /// Sent with microsecond precision, finer components are truncated.
     pub interval: Duration,
     /// CAN identifier.
     pub id: Id,
     /// CAN data length code.
     pub dlc: u8,
     /// CAN data.
-    pub data: Vec<u8, MAX_FRAME_DATA_LEN>,
+    pub data: Vec<u8, MAX_DATA_LEN>,
+}
+
+impl Add {
+    /// Create a new [`Add`] command.
+    ///
+    /// Returns `None` if `data` is longer than [`MAX_DATA_LEN`]. Sub
+    /// microsecond components of `interval` are truncated on the wire.
+    pub fn new(interval: Duration, id: Id, data: &[u8]) -> Option<Self> {
+        warn_truncated(interval);
+
+        Some(Self {
+            interval,
+            id,
+            dlc: data.len() as u8,
+            data: Vec::from_slice(data).ok()?,
+        })
+    }
 }
 
 impl embedded_can::Frame for Add {
-    fn new(_id: impl Into<Id>, _data: &[u8]) -> Option<Self> {
-        unimplemented!()
+    fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+        Add::new(Duration::ZERO, id.into(), data)
     }
 
-    fn new_remote(_id: impl Into<Id>, _dlc: usize) -> Option<Self> {
-        unimplemented!()
+    fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
+        if dlc > MAX_DATA_LEN {
+            return None;
+        }
+
+        Some(Self {
+            interval: Duration::ZERO,
+            id: id.into(),
+            dlc: dlc as u8,
+            data: Vec::new(),
+        })
     }
 
     fn id(&self) -> Id {
@@ -128,28 +428,52 @@ impl embedded_can::Frame for Add {
     }
 }
 
-fn add<'a>(input: &'a str) -> IResult<&'a str, Add> {
-    let (input, (secs, micros, id, dlc, data)) = delimited(
-        tag("< add "),
-        tuple((
-            terminated(map_res(digit1, u64::from_str), char(' ')),
-            terminated(map_res(digit1, u64::from_str), char(' ')),
-            id,
-            terminated(map_res(digit1, u8::from_str), char(' ')),
-            map(
-                take_while(|c: char| c.is_digit(16) || c == ' '),
-                |bytes: &str| {
-                    bytes
-                        .split_whitespace()
-                        .filter_map(|b| u8::from_str_radix(b, 16).ok())
-                        .collect()
+impl Display for Add {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "< add {} {} ",
+            self.interval.as_secs(),
+            self.interval.subsec_micros()
+        )?;
+        write_id(f, self.id)?;
+        write!(f, " {} ", self.dlc)?;
+
+        for byte in &self.data {
+            write!(f, "{:02X} ", byte)?;
+        }
+
+        write!(f, ">")
+    }
+}
+
+#[inline]
+fn add(input: &str) -> IResult<'_, Add> {
+    let (input, (secs, micros, id, dlc, data)) = keyword(
+        "< add ",
+        "malformed add command",
+        terminated(
+            verify(
+                tuple((
+                    terminated(map_res(digit1, u64::from_str), char(' ')),
+                    terminated(map_res(digit1, u64::from_str), char(' ')),
+                    id,
+                    terminated(map_res(digit1, u8::from_str), char(' ')),
+                    map_res(
+                        take_while(|c: char| c.is_ascii_hexdigit() || c == ' '),
+                        parse_hex_bytes::<MAX_DATA_LEN>,
+                    ),
+                )),
+                // remote frames carry a data length code but no data
+                |(.., dlc, data)| {
+                    data.is_empty() || *dlc as usize == data.len()
                 },
             ),
-        )),
-        char('>'),
+            char('>'),
+        ),
     )(input)?;
 
-    let interval = Duration::from_secs(secs) + Duration::from_micros(micros);
+    let interval = seconds_micros(input, secs, micros)?;
 
     Ok((
         input,
@@ -162,6 +486,137 @@ fn add<'a>(input: &'a str) -> IResult<&'a str, Add> {
     ))
 }
 
+/// CAN identifier key, for use in hash maps.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct CanId(pub Id);
+
+impl From<Id> for CanId {
+    fn from(id: Id) -> Self {
+        Self(id)
+    }
+}
+
+impl Display for CanId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write_id(f, self.0)
+    }
+}
+
+/// Table of cyclic jobs, keyed by CAN identifier.
+///
+/// `N` is the maximum number of jobs and must be a power of two.
+///
+/// # Example
+/// ```rust
+/// use socketcand::wire::{command, BcmJobTable, Command};
+///
+/// let mut jobs = BcmJobTable::<4>::new();
+///
+/// let (_, cmd) = command("< add 1 0 123 1 AA >").unwrap();
+/// if let Command::Add(add) = cmd {
+///     let id = add.id;
+///     jobs.insert(add).unwrap();
+///     assert!(jobs.get(id).is_some());
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BcmJobTable<const N: usize> {
+    jobs: FnvIndexMap<CanId, Add, N>,
+}
+
+impl<const N: usize> BcmJobTable<N> {
+    /// Create an empty [`BcmJobTable`].
+    pub fn new() -> Self {
+        Self {
+            jobs: FnvIndexMap::new(),
+        }
+    }
+
+    /// Insert a job, replacing any job with the same identifier.
+    ///
+    /// Returns the replaced job, or `Err(job)` if the table is full.
+    pub fn insert(&mut self, job: Add) -> Result<Option<Add>, Add> {
+        self.jobs.insert(CanId(job.id), job).map_err(|(_, job)| job)
+    }
+
+    /// Remove the job with the given identifier.
+    pub fn remove(&mut self, id: Id) -> Option<Add> {
+        self.jobs.remove(&CanId(id))
+    }
+
+    /// Returns the job with the given identifier.
+    pub fn get(&self, id: Id) -> Option<&Add> {
+        self.jobs.get(&CanId(id))
+    }
+
+    /// Returns a mutable reference to the job with the given identifier.
+    pub fn get_mut(&mut self, id: Id) -> Option<&mut Add> {
+        self.jobs.get_mut(&CanId(id))
+    }
+
+    /// Keep only the jobs for which `f` returns `true`.
+    pub fn retain(&mut self, mut f: impl FnMut(&Add) -> bool) {
+        self.jobs.retain(|_, job| f(job));
+    }
+
+    /// Returns `true` if a job exists for the given identifier.
+    pub fn contains(&self, id: Id) -> bool {
+        self.jobs.contains_key(&CanId(id))
+    }
+
+    /// Returns the number of jobs.
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Returns `true` if there are no jobs.
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Returns an iterator over the jobs.
+    pub fn iter(&self) -> JobIter<'_, N> {
+        JobIter {
+            jobs: self.jobs.values(),
+            _table: PhantomData,
+        }
+    }
+}
+
+impl<'a, const N: usize> IntoIterator for &'a BcmJobTable<N> {
+    type Item = &'a Add;
+    type IntoIter = JobIter<'a, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the jobs in a [`BcmJobTable`].
+///
+/// Jobs are yielded in insertion order, except that removing a job moves the
+/// last job into its place.
+///
+/// Created by [`BcmJobTable::iter`].
+pub struct JobIter<'a, const N: usize> {
+    jobs: IndexMapValues<'a, CanId, Add>,
+    _table: PhantomData<&'a BcmJobTable<N>>,
+}
+
+impl<const N: usize> core::fmt::Debug for JobIter<'_, N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("JobIter").finish_non_exhaustive()
+    }
+}
+
+impl<'a, const N: usize> Iterator for JobIter<'a, N> {
+    type Item = &'a Add;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.jobs.next()
+    }
+}
+
 /// Frame job update command.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Update {
@@ -170,16 +625,55 @@ pub struct Update {
     /// CAN data length code.
     pub dlc: u8,
     /// CAN data.
-    pub data: Vec<u8, MAX_FRAME_DATA_LEN>,
+    pub data: Vec<u8, MAX_DATA_LEN>,
+}
+
+/// [`Update`] construction error.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum UpdateError {
+    /// More than 8 data bytes were given.
+    DataTooLong,
+    /// The data length code does not match the number of data bytes.
+    DlcMismatch,
+}
+
+impl Update {
+    /// Create a new [`Update`] command.
+    pub fn new(id: Id, dlc: u8, data: &[u8]) -> Result<Self, UpdateError> {
+        let data =
+            Vec::from_slice(data).map_err(|_| UpdateError::DataTooLong)?;
+
+        if dlc as usize != data.len() {
+            return Err(UpdateError::DlcMismatch);
+        }
+
+        Ok(Self { id, dlc, data })
+    }
+}
+
+impl Display for Update {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< update ")?;
+        write_id(f, self.id)?;
+        write!(f, " {} ", self.dlc)?;
+
+        for byte in &self.data {
+            write!(f, "{:02X} ", byte)?;
+        }
+
+        write!(f, ">")
+    }
 }
 
 impl embedded_can::Frame for Update {
-    fn new(_id: impl Into<Id>, _data: &[u8]) -> Option<Self> {
-        unimplemented!()
+    fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+        Update::new(id.into(), data.len() as u8, data).ok()
     }
 
     fn new_remote(_id: impl Into<Id>, _dlc: usize) -> Option<Self> {
-        unimplemented!()
+        // updates replace the data of a job
+        None
     }
 
     fn id(&self) -> Id {
@@ -206,23 +700,25 @@ impl embedded_can::Frame for Update {
     }
 }
 
-fn update<'a>(input: &'a str) -> IResult<&'a str, Update> {
-    let (input, (id, dlc, data)) = delimited(
-        tag("< update "),
-        tuple((
-            id,
-            terminated(map_res(digit1, u8::from_str), char(' ')),
-            map(
-                take_while(|c: char| c.is_digit(16) || c == ' '),
-                |bytes: &str| {
-                    bytes
-                        .split_whitespace()
-                        .filter_map(|b| u8::from_str_radix(b, 16).ok())
-                        .collect()
-                },
+#[inline]
+fn update(input: &str) -> IResult<'_, Update> {
+    let (input, (id, dlc, data)) = keyword(
+        "< update ",
+        "malformed update command",
+        terminated(
+            verify(
+                tuple((
+                    id,
+                    terminated(map_res(digit1, u8::from_str), char(' ')),
+                    map_res(
+                        take_while(|c: char| c.is_ascii_hexdigit() || c == ' '),
+                        parse_hex_bytes::<MAX_DATA_LEN>,
+                    ),
+                )),
+                |(.., dlc, data)| *dlc as usize == data.len(),
             ),
-        )),
-        char('>'),
+            char('>'),
+        ),
     )(input)?;
 
     Ok((input, Update { id, dlc, data }))
@@ -235,8 +731,28 @@ pub struct Delete {
     pub id: Id,
 }
 
-fn delete<'a>(input: &'a str) -> IResult<&'a str, Delete> {
-    let (input, id) = delimited(tag("< delete "), id, char('>'))(input)?;
+impl Delete {
+    /// Create a new [`Delete`] command.
+    pub fn new(id: Id) -> Self {
+        Self { id }
+    }
+}
+
+impl Display for Delete {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< delete ")?;
+        write_id(f, self.id)?;
+        write!(f, " >")
+    }
+}
+
+#[inline]
+fn delete(input: &str) -> IResult<'_, Delete> {
+    let (input, id) = keyword(
+        "< delete ",
+        "malformed delete command",
+        terminated(id, char('>')),
+    )(input)?;
 
     Ok((input, Delete { id }))
 }
@@ -249,16 +765,20 @@ pub struct Send {
     /// CAN data length code.
     pub dlc: u8,
     /// CAN data.
-    pub data: Vec<u8, MAX_FRAME_DATA_LEN>,
+    pub data: Vec<u8, MAX_DATA_LEN>,
 }
 
 impl embedded_can::Frame for Send {
-    fn new(_id: impl Into<Id>, _data: &[u8]) -> Option<Self> {
-        unimplemented!()
+    fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+        Send::new(id.into()).data(data).build().ok()
     }
 
-    fn new_remote(_id: impl Into<Id>, _dlc: usize) -> Option<Self> {
-        unimplemented!()
+    fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
+        Send::new(id.into())
+            .dlc(u8::try_from(dlc).ok()?)
+            .rtr()
+            .build()
+            .ok()
     }
 
     fn id(&self) -> Id {
@@ -285,52 +805,212 @@ impl embedded_can::Frame for Send {
     }
 }
 
-fn send<'a>(input: &'a str) -> IResult<&'a str, Send> {
-    let (input, (id, dlc, data)) = delimited(
-        tag("< send "),
-        tuple((
+/// [`Send`] construction error.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum SendError {
+    /// More than 8 data bytes were given.
+    DataTooLong,
+    /// The data length code is greater than 8.
+    DlcTooLarge,
+    /// The data length code does not match the number of data bytes.
+    DlcMismatch,
+}
+
+impl Send {
+    /// Start building a [`Send`] command for the given identifier.
+    ///
+    /// # Example
+    /// ```rust
+    /// use embedded_can::{Id, StandardId};
+    /// use socketcand::wire::Send;
+    ///
+    /// let id = Id::Standard(StandardId::new(0x123).unwrap());
+    ///
+    /// let send = Send::new(id).data(&[0x01, 0x02]).build().unwrap();
+    /// assert_eq!(send.dlc, 2);
+    ///
+    /// let remote = Send::new(id).dlc(4).rtr().build().unwrap();
+    /// assert!(remote.data.is_empty());
+    /// ```
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(id: Id) -> SendBuilder {
+        SendBuilder {
             id,
-            terminated(map_res(digit1, u8::from_str), char(' ')),
-            map(
-                take_while(|c: char| c.is_digit(16) || c == ' '),
-                |bytes: &str| {
-                    if bytes.trim().is_empty() {
-                        Vec::new()
-                    } else {
-                        bytes
-                            .split_whitespace()
-                            .filter_map(|b| u8::from_str_radix(b, 16).ok())
-                            .collect()
-                    }
-                },
+            dlc: None,
+            data: Vec::new(),
+            data_too_long: false,
+        }
+    }
+}
+
+/// Builder for [`Send`] commands, created with [`Send::new`].
+#[derive(Debug, Clone)]
+pub struct SendBuilder {
+    id: Id,
+    dlc: Option<u8>,
+    data: Vec<u8, MAX_DATA_LEN>,
+    data_too_long: bool,
+}
+
+impl SendBuilder {
+    /// Set the frame data.
+    ///
+    /// The data length code follows the data unless set with
+    /// [`SendBuilder::dlc`].
+    pub fn data(mut self, data: &[u8]) -> Self {
+        match Vec::from_slice(data) {
+            Ok(data) => {
+                self.data = data;
+                self.data_too_long = false;
+            }
+            Err(_) => self.data_too_long = true,
+        }
+        self
+    }
+
+    /// Set the data length code explicitly.
+    pub fn dlc(mut self, dlc: u8) -> Self {
+        self.dlc = Some(dlc);
+        self
+    }
+
+    /// Make this a remote frame, which carries a data length code but no
+    /// data.
+    pub fn rtr(mut self) -> Self {
+        self.data.clear();
+        self.data_too_long = false;
+        self
+    }
+
+    /// Build the [`Send`] command.
+    pub fn build(self) -> Result<Send, SendError> {
+        if self.data_too_long {
+            return Err(SendError::DataTooLong);
+        }
+
+        let dlc = self.dlc.unwrap_or(self.data.len() as u8);
+
+        if dlc as usize > MAX_DATA_LEN {
+            return Err(SendError::DlcTooLarge);
+        }
+
+        if !self.data.is_empty() && dlc as usize != self.data.len() {
+            return Err(SendError::DlcMismatch);
+        }
+
+        Ok(Send {
+            id: self.id,
+            dlc,
+            data: self.data,
+        })
+    }
+}
+
+impl Display for Send {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< send ")?;
+        write_id(f, self.id)?;
+        write!(f, " {} ", self.dlc)?;
+
+        for byte in &self.data {
+            write!(f, "{:02X} ", byte)?;
+        }
+
+        write!(f, ">")
+    }
+}
+
+#[inline]
+fn send(input: &str) -> IResult<'_, Send> {
+    let (input, (id, dlc, data)) = keyword(
+        "< send ",
+        "malformed send command",
+        terminated(
+            verify(
+                tuple((
+                    id,
+                    terminated(map_res(digit1, u8::from_str), char(' ')),
+                    map_res(
+                        take_while(|c: char| c.is_ascii_hexdigit() || c == ' '),
+                        parse_hex_bytes::<MAX_DATA_LEN>,
+                    ),
+                )),
+                // remote frames carry a data length code but no data
+                |(_, dlc, data)| data.is_empty() || *dlc as usize == data.len(),
             ),
-        )),
-        char('>'),
+            char('>'),
+        ),
     )(input)?;
 
     Ok((input, Send { id, dlc, data }))
 }
 
 /// Content filter command.
+///
+/// The socketcand daemon treats `data` as a content mask: a frame is
+/// delivered when any of the masked bits change. Some implementations instead
+/// match frames against `data` using a separate `mask`, which is sent as a
+/// second group of `dlc` bytes after the data.
+///
+/// Sent as `< filter_inv ... >`, the filter is inverted: frames are delivered
+/// when they do *not* match, e.g. to watch for a signal leaving its expected
+/// value.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Filter {
     /// Update rate.
+    ///
+    /// Sent with microsecond precision, finer components are truncated.
     pub interval: Duration,
     /// CAN identifier.
     pub id: Id,
     /// CAN data length code.
     pub dlc: u8,
     /// CAN data.
-    pub data: Vec<u8, MAX_FRAME_DATA_LEN>,
+    pub data: Vec<u8, MAX_DATA_LEN>,
+    /// Data mask, empty unless sent separately from the data.
+    pub mask: Vec<u8, MAX_DATA_LEN>,
+    /// Deliver frames that do not match instead of those that do.
+    pub invert: bool,
+}
+
+impl Filter {
+    /// Create a new [`Filter`] command matching `data`.
+    ///
+    /// Returns `None` if `data` is longer than [`MAX_DATA_LEN`]. Sub
+    /// microsecond components of `interval` are truncated on the wire.
+    pub fn new(interval: Duration, id: Id, data: &[u8]) -> Option<Self> {
+        warn_truncated(interval);
+
+        Some(Self {
+            interval,
+            id,
+            dlc: data.len() as u8,
+            data: Vec::from_slice(data).ok()?,
+            mask: Vec::new(),
+            invert: false,
+        })
+    }
 }
 
 impl embedded_can::Frame for Filter {
-    fn new(_id: impl Into<Id>, _data: &[u8]) -> Option<Self> {
-        unimplemented!()
+    fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+        Filter::new(Duration::ZERO, id.into(), data)
     }
 
-    fn new_remote(_id: impl Into<Id>, _dlc: usize) -> Option<Self> {
-        unimplemented!()
+    fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
+        if dlc > MAX_DATA_LEN {
+            return None;
+        }
+
+        Some(Self {
+            interval: Duration::ZERO,
+            id: id.into(),
+            dlc: dlc as u8,
+            data: Vec::new(),
+            mask: Vec::new(),
+            invert: false,
+        })
     }
 
     fn id(&self) -> Id {
@@ -357,28 +1037,73 @@ impl embedded_can::Frame for Filter {
     }
 }
 
-fn filter<'a>(input: &'a str) -> IResult<&'a str, Filter> {
-    let (input, (secs, micros, id, dlc, data)) = delimited(
-        tag("< filter "),
-        tuple((
-            terminated(map_res(digit1, u64::from_str), char(' ')),
-            terminated(map_res(digit1, u64::from_str), char(' ')),
-            id,
-            terminated(map_res(digit1, u8::from_str), char(' ')),
-            map(
-                take_while(|c: char| c.is_digit(16) || c == ' '),
-                |bytes: &str| {
-                    bytes
-                        .split_whitespace()
-                        .filter_map(|b| u8::from_str_radix(b, 16).ok())
-                        .collect()
+impl Display for Filter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "< {} {} {} ",
+            if self.invert { "filter_inv" } else { "filter" },
+            self.interval.as_secs(),
+            self.interval.subsec_micros()
+        )?;
+        write_id(f, self.id)?;
+        write!(f, " {} ", self.dlc)?;
+
+        for byte in self.data.iter().chain(&self.mask) {
+            write!(f, "{:02X} ", byte)?;
+        }
+
+        write!(f, ">")
+    }
+}
+
+#[inline]
+fn filter(input: &str) -> IResult<'_, Filter> {
+    let (input, invert) = alt((
+        map(tag("< filter "), |_| false),
+        map(tag("< filter_inv "), |_| true),
+    ))(input)?;
+
+    let (input, (secs, micros, id, dlc, bytes)) = cut(context(
+        "malformed filter command",
+        terminated(
+            verify(
+                tuple((
+                    terminated(map_res(digit1, u64::from_str), char(' ')),
+                    terminated(map_res(digit1, u64::from_str), char(' ')),
+                    id,
+                    terminated(map_res(digit1, u8::from_str), char(' ')),
+                    map_res(
+                        take_while(|c: char| c.is_ascii_hexdigit() || c == ' '),
+                        |bytes: &str| {
+                            let mut out =
+                                Vec::<u8, { 2 * MAX_DATA_LEN }>::new();
+
+                            for byte in bytes.split_whitespace() {
+                                let byte = u8::from_str_radix(byte, 16)
+                                    .map_err(|_| "Invalid hex byte.")?;
+                                out.push(byte)
+                                    .map_err(|_| "Too many bytes.")?;
+                            }
+
+                            Ok::<_, &str>(out)
+                        },
+                    ),
+                )),
+                // data bytes for the data length code, optionally followed by
+                // the same number of mask bytes
+                |(_, _, _, dlc, bytes)| {
+                    let dlc = *dlc as usize;
+                    dlc <= MAX_DATA_LEN
+                        && (bytes.len() == dlc || bytes.len() == 2 * dlc)
                 },
             ),
-        )),
-        char('>'),
-    )(input)?;
+            char('>'),
+        ),
+    ))(input)?;
 
-    let interval = Duration::from_secs(secs) + Duration::from_micros(micros);
+    let interval = seconds_micros(input, secs, micros)?;
+    let (data, mask) = bytes.split_at(dlc as usize);
 
     Ok((
         input,
@@ -386,288 +1111,4034 @@ fn filter<'a>(input: &'a str) -> IResult<&'a str, Filter> {
             interval,
             id,
             dlc,
-            data,
+            // lengths are checked by the parser
+            data: Vec::from_slice(data).unwrap_or_default(),
+            mask: Vec::from_slice(mask).unwrap_or_default(),
+            invert,
         },
     ))
 }
 
 /// Echo command.
-#[derive(Debug, PartialEq, Clone)]
+///
+/// A client may include a nonce (`< echo NONCE >`) to measure latency. The
+/// server then replies with the nonce and the time it took to respond, in
+/// microseconds (`< echo NONCE ELAPSED_USECS >`).
+#[derive(Debug, Default, PartialEq, Clone)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
-pub struct Echo;
+pub struct Echo {
+    /// Client supplied nonce.
+    pub nonce: Option<String<MAX_ECHO_NONCE_LEN>>,
+    /// Server processing time, only present in replies to a nonce.
+    pub elapsed: Option<Duration>,
+}
+
+impl Display for Echo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< echo ")?;
+
+        if let Some(nonce) = &self.nonce {
+            write!(f, "{} ", nonce)?;
+
+            if let Some(elapsed) = self.elapsed {
+                write!(f, "{} ", elapsed.as_micros())?;
+            }
+        }
 
-fn echo<'a>(input: &'a str) -> IResult<&'a str, Echo> {
-    let (input, _) = tag("< echo >")(input)?;
+        write!(f, ">")
+    }
+}
 
-    Ok((input, Echo))
+#[inline]
+fn echo(input: &str) -> IResult<'_, Echo> {
+    alt((
+        map(tag("< echo >"), |_| Echo::default()),
+        map(
+            keyword(
+                "< echo ",
+                "malformed echo command",
+                terminated(
+                    tuple((
+                        map_res(
+                            take_till1(|c: char| c == ' ' || c == '>'),
+                            String::try_from,
+                        ),
+                        opt(preceded(
+                            char(' '),
+                            map_res(digit1, u64::from_str),
+                        )),
+                    )),
+                    tag(" >"),
+                ),
+            ),
+            |(nonce, elapsed)| Echo {
+                nonce: Some(nonce),
+                elapsed: elapsed.map(Duration::from_micros),
+            },
+        ),
+    ))(input)
 }
 
 /// Enter raw mode command.
 #[derive(Debug, PartialEq, Clone)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
-pub struct RawMode;
-
-fn raw_mode<'a>(input: &'a str) -> IResult<&'a str, RawMode> {
-    let (input, _) = tag("< rawmode >")(input)?;
-
-    Ok((input, RawMode))
+pub struct RawMode {
+    /// Echo frames sent by the client back to it (`< rawmode loopback >`).
+    pub loopback: bool,
+}
+
+impl Display for RawMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        if self.loopback {
+            write!(f, "< rawmode loopback >")
+        } else {
+            write!(f, "< rawmode >")
+        }
+    }
+}
+
+#[inline]
+fn raw_mode(input: &str) -> IResult<'_, RawMode> {
+    let (input, loopback) = alt((
+        map(tag("< rawmode >"), |_| false),
+        map(tag("< rawmode loopback >"), |_| true),
+    ))(input)?;
+
+    Ok((input, RawMode { loopback }))
 }
 
 /// Enter broadcast mode command.
 ///
 /// Broadcase mode is the default mode.
+#[derive(Debug, Default, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct BroadcastMode {
+    /// Number of frames to broadcast before leaving broadcast mode, or
+    /// unlimited if `None`.
+    pub count: Option<u32>,
+}
+
+impl Display for BroadcastMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self.count {
+            Some(count) => write!(f, "< bcmode {} >", count),
+            None => write!(f, "< bcmode >"),
+        }
+    }
+}
+
+#[inline]
+fn broadcast_mode(input: &str) -> IResult<'_, BroadcastMode> {
+    let (input, count) = alt((
+        map(tag("< bcmode >"), |_| None),
+        map(
+            keyword(
+                "< bcmode ",
+                "malformed bcmode command",
+                terminated(
+                    map_res(digit1, |v: &str| u32::from_str(v)),
+                    tag(" >"),
+                ),
+            ),
+            Some,
+        ),
+    ))(input)?;
+
+    Ok((input, BroadcastMode { count }))
+}
+
+/// Enter control mode command.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct ControlMode {
+    /// Bus name (e.g. `can0`), required by some socketcand versions.
+    pub bus: Option<String<MAX_INTERFACE_NAME_LEN>>,
+}
+
+impl Display for ControlMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match &self.bus {
+            Some(bus) => write!(f, "< controlmode {} >", bus),
+            None => write!(f, "< controlmode >"),
+        }
+    }
+}
+
+#[inline]
+fn control_mode(input: &str) -> IResult<'_, ControlMode> {
+    let (input, bus) = alt((
+        map(tag("< controlmode >"), |_| None),
+        map(
+            keyword(
+                "< controlmode ",
+                "malformed controlmode command",
+                terminated(
+                    map_res(
+                        take_till1(|c: char| c == ' ' || c == '>'),
+                        String::try_from,
+                    ),
+                    tag(" >"),
+                ),
+            ),
+            Some,
+        ),
+    ))(input)?;
+
+    Ok((input, ControlMode { bus }))
+}
+
+/// Enter ISO-TP mode command.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct IsoTpMode;
+
+impl Display for IsoTpMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< isotpmode >")
+    }
+}
+
+#[inline]
+fn iso_tp_mode(input: &str) -> IResult<'_, IsoTpMode> {
+    let (input, _) = tag("< isotpmode >")(input)?;
+
+    Ok((input, IsoTpMode))
+}
+
+/// Shortest accepted statistics interval.
+pub const MIN_STATS_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Longest accepted statistics interval.
+pub const MAX_STATS_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Statistics setting command.
+///
+/// The interval must be between [`MIN_STATS_INTERVAL`] and
+/// [`MAX_STATS_INTERVAL`], or zero to turn periodic statistics off.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Statistics {
+    /// Send rate milliseconds.
+    pub interval: Duration,
+}
+
+impl Display for Statistics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< statistics {} >", self.interval.as_millis())
+    }
+}
+
+#[inline]
+fn statistics(input: &str) -> IResult<'_, Statistics> {
+    let (input, interval) = keyword(
+        "< statistics ",
+        "malformed statistics command",
+        terminated(
+            verify(
+                map(map_res(digit1, |v: &str| u64::from_str(v)), |millis| {
+                    Duration::from_millis(millis)
+                }),
+                |interval: &Duration| {
+                    interval.is_zero()
+                        || (MIN_STATS_INTERVAL..=MAX_STATS_INTERVAL)
+                            .contains(interval)
+                },
+            ),
+            tag(" >"),
+        ),
+    )(input)?;
+
+    Ok((input, Statistics { interval }))
+}
+
+/// Authentication command.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Auth {
+    /// Authentication token.
+    pub token: String<MAX_AUTH_TOKEN_LEN>,
+}
+
+impl Display for Auth {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< auth {} >", self.token)
+    }
+}
+
+#[inline]
+fn auth(input: &str) -> IResult<'_, Auth> {
+    let (input, token) = keyword(
+        "< auth ",
+        "malformed auth command",
+        terminated(
+            map_res(
+                take_till1(|c: char| c == ' ' || c == '>'),
+                String::try_from,
+            ),
+            tag(" >"),
+        ),
+    )(input)?;
+
+    Ok((input, Auth { token }))
+}
+
+/// List cyclic jobs command.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct ListJobs;
+
+impl Display for ListJobs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< list_jobs >")
+    }
+}
+
+#[inline]
+fn list_jobs(input: &str) -> IResult<'_, ListJobs> {
+    let (input, _) = tag("< list_jobs >")(input)?;
+
+    Ok((input, ListJobs))
+}
+
+/// Get busses command.
+///
+/// The server replies with the busses it provides, e.g. `< busses can0 can1 >`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct GetBusses;
+
+impl Display for GetBusses {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< get_busses >")
+    }
+}
+
+#[inline]
+fn get_busses(input: &str) -> IResult<'_, GetBusses> {
+    let (input, _) = tag("< get_busses >")(input)?;
+
+    Ok((input, GetBusses))
+}
+
+/// Broadcast manager flags, as defined in `linux/can/bcm.h`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct BcmFlags(pub u32);
+
+impl BcmFlags {
+    /// Set the interval values.
+    pub const SETTIMER: Self = Self(0x0001);
+    /// Start the timer with the interval values.
+    pub const STARTTIMER: Self = Self(0x0002);
+    /// Create a notification when the transmit count reaches zero.
+    pub const TX_COUNTEVT: Self = Self(0x0004);
+    /// Send a changed frame immediately.
+    pub const TX_ANNOUNCE: Self = Self(0x0008);
+    /// Copy the CAN identifier into the frames.
+    pub const TX_CP_CAN_ID: Self = Self(0x0010);
+    /// Filter by CAN identifier only.
+    pub const RX_FILTER_ID: Self = Self(0x0020);
+    /// Notify on data length code changes.
+    pub const RX_CHECK_DLC: Self = Self(0x0040);
+    /// Do not start the timeout monitor automatically.
+    pub const RX_NO_AUTOTIMER: Self = Self(0x0080);
+    /// Notify when a timed out frame is received again.
+    pub const RX_ANNOUNCE_RESUME: Self = Self(0x0100);
+    /// Reset the index for multiple frame transmissions.
+    pub const TX_RESET_MULTI_IDX: Self = Self(0x0200);
+    /// Send a reply to remote frames.
+    pub const RX_RTR_FRAME: Self = Self(0x0400);
+    /// Frames are CAN FD frames.
+    pub const CAN_FD_FRAME: Self = Self(0x0800);
+
+    /// Returns the raw flag bits.
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns `true` if all flags in `other` are set.
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for BcmFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Parse broadcast manager flags, written as hex.
+#[inline]
+fn bcm_flags(input: &str) -> IResult<'_, BcmFlags> {
+    map(
+        terminated(
+            map_res(hex_digit1, |flags: &str| u32::from_str_radix(flags, 16)),
+            char(' '),
+        ),
+        BcmFlags,
+    )(input)
+}
+
+/// Broadcast manager transmit job setup command.
+///
+/// Unlike [`Add`], the broadcast manager flags are given explicitly.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BcmTxSetup {
+    /// Broadcast manager flags.
+    pub flags: BcmFlags,
+    /// Interval.
+    pub interval: Duration,
+    /// CAN identifier.
+    pub id: Id,
+    /// CAN data length code.
+    pub dlc: u8,
+    /// CAN data.
+    pub data: Vec<u8, MAX_DATA_LEN>,
+}
+
+impl Display for BcmTxSetup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "< tx_setup {:X} {} {} ",
+            self.flags.bits(),
+            self.interval.as_secs(),
+            self.interval.subsec_micros()
+        )?;
+        write_id(f, self.id)?;
+        write!(f, " {} ", self.dlc)?;
+
+        for byte in &self.data {
+            write!(f, "{:02X} ", byte)?;
+        }
+
+        write!(f, ">")
+    }
+}
+
+#[inline]
+fn bcm_tx_setup(input: &str) -> IResult<'_, BcmTxSetup> {
+    let (input, (flags, secs, micros, id, dlc, data)) = keyword(
+        "< tx_setup ",
+        "malformed tx_setup command",
+        terminated(
+            verify(
+                tuple((
+                    bcm_flags,
+                    terminated(map_res(digit1, u64::from_str), char(' ')),
+                    terminated(map_res(digit1, u64::from_str), char(' ')),
+                    id,
+                    terminated(map_res(digit1, u8::from_str), char(' ')),
+                    map_res(
+                        take_while(|c: char| c.is_ascii_hexdigit() || c == ' '),
+                        parse_hex_bytes::<MAX_DATA_LEN>,
+                    ),
+                )),
+                // remote frames carry a data length code but no data
+                |(.., dlc, data)| {
+                    data.is_empty() || *dlc as usize == data.len()
+                },
+            ),
+            char('>'),
+        ),
+    )(input)?;
+
+    let interval = seconds_micros(input, secs, micros)?;
+
+    Ok((
+        input,
+        BcmTxSetup {
+            flags,
+            interval,
+            id,
+            dlc,
+            data,
+        },
+    ))
+}
+
+/// Broadcast manager receive filter setup command.
+///
+/// Frames matching `id` are delivered to the client when the bits selected by
+/// `mask` change.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BcmRxSetup {
+    /// Broadcast manager flags.
+    pub flags: BcmFlags,
+    /// Timeout or throttle interval.
+    pub interval: Duration,
+    /// CAN identifier.
+    pub id: Id,
+    /// CAN data length code.
+    pub dlc: u8,
+    /// Content mask.
+    pub mask: Vec<u8, MAX_DATA_LEN>,
+}
+
+impl Display for BcmRxSetup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "< rx_setup {:X} {} {} ",
+            self.flags.bits(),
+            self.interval.as_secs(),
+            self.interval.subsec_micros()
+        )?;
+        write_id(f, self.id)?;
+        write!(f, " {} ", self.dlc)?;
+
+        for byte in &self.mask {
+            write!(f, "{:02X} ", byte)?;
+        }
+
+        write!(f, ">")
+    }
+}
+
+#[inline]
+fn bcm_rx_setup(input: &str) -> IResult<'_, BcmRxSetup> {
+    let (input, (flags, secs, micros, id, dlc, mask)) = keyword(
+        "< rx_setup ",
+        "malformed rx_setup command",
+        terminated(
+            verify(
+                tuple((
+                    bcm_flags,
+                    terminated(map_res(digit1, u64::from_str), char(' ')),
+                    terminated(map_res(digit1, u64::from_str), char(' ')),
+                    id,
+                    terminated(map_res(digit1, u8::from_str), char(' ')),
+                    map_res(
+                        take_while(|c: char| c.is_ascii_hexdigit() || c == ' '),
+                        parse_hex_bytes::<MAX_DATA_LEN>,
+                    ),
+                )),
+                // remote frames carry a data length code but no data
+                |(.., dlc, data)| {
+                    data.is_empty() || *dlc as usize == data.len()
+                },
+            ),
+            char('>'),
+        ),
+    )(input)?;
+
+    let interval = seconds_micros(input, secs, micros)?;
+
+    Ok((
+        input,
+        BcmRxSetup {
+            flags,
+            interval,
+            id,
+            dlc,
+            mask,
+        },
+    ))
+}
+
+/// ISO-TP addressing mode.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum IsoTpAddrMode {
+    /// Normal addressing, using the CAN identifier only.
+    Normal,
+    /// Extended addressing, with the target address in the first data byte.
+    Extended,
+    /// Mixed addressing, with an address extension in the first data byte.
+    Mixed,
+}
+
+impl IsoTpAddrMode {
+    fn as_str(&self) -> &'static str {
+        match self {
+            IsoTpAddrMode::Normal => "normal",
+            IsoTpAddrMode::Extended => "extended",
+            IsoTpAddrMode::Mixed => "mixed",
+        }
+    }
+}
+
+/// ISO-TP channel configuration command.
+///
+/// Sent as `< isotpconf SRC DST MODE BLOCK_SIZE STMIN [PADDING] >`, where
+/// `MODE` is one of `normal`, `extended` or `mixed` and the optional padding
+/// byte is written as hex.
+#[derive(Debug, PartialEq, Clone)]
+pub struct IsoTpConf {
+    /// CAN identifier used to transmit.
+    pub src_id: Id,
+    /// CAN identifier to receive from.
+    pub dst_id: Id,
+    /// Addressing mode.
+    pub addr_mode: IsoTpAddrMode,
+    /// Number of consecutive frames sent before waiting for flow control.
+    pub block_size: u8,
+    /// Minimum separation time between consecutive frames.
+    pub stmin: u8,
+    /// Byte used to pad frames to 8 bytes, if padding is enabled.
+    pub padding: Option<u8>,
+}
+
+impl Display for IsoTpConf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< isotpconf ")?;
+        write_id(f, self.src_id)?;
+        write!(f, " ")?;
+        write_id(f, self.dst_id)?;
+        write!(
+            f,
+            " {} {} {} ",
+            self.addr_mode.as_str(),
+            self.block_size,
+            self.stmin
+        )?;
+
+        if let Some(padding) = self.padding {
+            write!(f, "{:02X} ", padding)?;
+        }
+
+        write!(f, ">")
+    }
+}
+
+#[inline]
+fn iso_tp_conf(input: &str) -> IResult<'_, IsoTpConf> {
+    let (input, (src_id, dst_id, addr_mode, block_size, stmin, padding)) =
+        keyword(
+            "< isotpconf ",
+            "malformed isotpconf command",
+            terminated(
+                verify(
+                    tuple((
+                        id,
+                        id,
+                        terminated(
+                            alt((
+                                map(tag("normal"), |_| IsoTpAddrMode::Normal),
+                                map(tag("extended"), |_| {
+                                    IsoTpAddrMode::Extended
+                                }),
+                                map(tag("mixed"), |_| IsoTpAddrMode::Mixed),
+                            )),
+                            char(' '),
+                        ),
+                        terminated(map_res(digit1, u8::from_str), char(' ')),
+                        terminated(map_res(digit1, u8::from_str), char(' ')),
+                        opt(terminated(
+                            map_res(hex_digit1, |b: &str| {
+                                u8::from_str_radix(b, 16)
+                            }),
+                            char(' '),
+                        )),
+                    )),
+                    // a channel cannot talk to itself
+                    |(src_id, dst_id, ..)| src_id != dst_id,
+                ),
+                char('>'),
+            ),
+        )(input)?;
+
+    Ok((
+        input,
+        IsoTpConf {
+            src_id,
+            dst_id,
+            addr_mode,
+            block_size,
+            stmin,
+            padding,
+        },
+    ))
+}
+
+/// No-operation command.
+///
+/// Used by monitoring tools to check that the connection is alive.
 #[derive(Debug, PartialEq, Clone)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
-pub struct BroadcastMode;
+pub struct Nop;
+
+impl Display for Nop {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< nop >")
+    }
+}
+
+#[inline]
+fn nop(input: &str) -> IResult<'_, Nop> {
+    let (input, _) = tag("< nop >")(input)?;
+
+    Ok((input, Nop))
+}
+
+/// Frame job range delete command, `< delete_range START END >`.
+///
+/// Removes every job with an identifier from `start` to `end` inclusive.
+/// Both identifiers must be of the same kind, standard or extended.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DeleteRange {
+    /// First CAN identifier in the range.
+    pub start: Id,
+    /// Last CAN identifier in the range.
+    pub end: Id,
+}
+
+/// Returns the raw value of an identifier and whether it is extended.
+fn raw_id(id: Id) -> (bool, u32) {
+    match id {
+        Id::Standard(id) => (false, id.as_raw() as u32),
+        Id::Extended(id) => (true, id.as_raw()),
+    }
+}
+
+impl DeleteRange {
+    /// Returns `true` if the identifier is within the range.
+    ///
+    /// Identifiers of a different kind than the range are never contained.
+    pub fn contains(&self, id: Id) -> bool {
+        let (extended, raw) = raw_id(id);
+        let (start_extended, start) = raw_id(self.start);
+        let (_, end) = raw_id(self.end);
+
+        extended == start_extended && (start..=end).contains(&raw)
+    }
+}
+
+impl Display for DeleteRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< delete_range ")?;
+        write_id(f, self.start)?;
+        write!(f, " ")?;
+        write_id(f, self.end)?;
+        write!(f, " >")
+    }
+}
+
+#[inline]
+fn delete_range(input: &str) -> IResult<'_, DeleteRange> {
+    let (input, (start, end)) = keyword(
+        "< delete_range ",
+        "malformed delete_range command",
+        terminated(
+            verify(tuple((id, id)), |(start, end)| {
+                let (start_extended, start) = raw_id(*start);
+                let (end_extended, end) = raw_id(*end);
+                start_extended == end_extended && start <= end
+            }),
+            char('>'),
+        ),
+    )(input)?;
+
+    Ok((input, DeleteRange { start, end }))
+}
+
+/// Frame job add command with a limited number of transmissions,
+/// `< add_count COUNT SECS MICROS ID DLC DATA >`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AddCount {
+    /// Number of times the frame is sent, at least one.
+    pub count: u32,
+    /// Interval.
+    pub interval: Duration,
+    /// CAN identifier.
+    pub id: Id,
+    /// CAN data length code.
+    pub dlc: u8,
+    /// CAN data.
+    pub data: Vec<u8, MAX_DATA_LEN>,
+}
+
+impl From<AddCount> for Add {
+    fn from(add: AddCount) -> Self {
+        Self {
+            interval: add.interval,
+            id: add.id,
+            dlc: add.dlc,
+            data: add.data,
+        }
+    }
+}
+
+impl Display for AddCount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "< add_count {} {} {} ",
+            self.count,
+            self.interval.as_secs(),
+            self.interval.subsec_micros()
+        )?;
+        write_id(f, self.id)?;
+        write!(f, " {} ", self.dlc)?;
+
+        for byte in &self.data {
+            write!(f, "{:02X} ", byte)?;
+        }
+
+        write!(f, ">")
+    }
+}
+
+#[inline]
+fn add_count(input: &str) -> IResult<'_, AddCount> {
+    let (input, (count, secs, micros, id, dlc, data)) = keyword(
+        "< add_count ",
+        "malformed add_count command",
+        terminated(
+            verify(
+                tuple((
+                    terminated(
+                        verify(map_res(digit1, u32::from_str), |count| {
+                            *count > 0
+                        }),
+                        char(' '),
+                    ),
+                    terminated(map_res(digit1, u64::from_str), char(' ')),
+                    terminated(map_res(digit1, u64::from_str), char(' ')),
+                    id,
+                    terminated(map_res(digit1, u8::from_str), char(' ')),
+                    map_res(
+                        take_while(|c: char| c.is_ascii_hexdigit() || c == ' '),
+                        parse_hex_bytes::<MAX_DATA_LEN>,
+                    ),
+                )),
+                // remote frames carry a data length code but no data
+                |(.., dlc, data)| {
+                    data.is_empty() || *dlc as usize == data.len()
+                },
+            ),
+            char('>'),
+        ),
+    )(input)?;
+
+    let interval = seconds_micros(input, secs, micros)?;
+
+    Ok((
+        input,
+        AddCount {
+            count,
+            interval,
+            id,
+            dlc,
+            data,
+        },
+    ))
+}
+
+/// Bit rates accepted by [`SetBaud`].
+pub const STANDARD_BAUD_RATES: [u32; 9] = [
+    10_000, 20_000, 50_000, 100_000, 125_000, 250_000, 500_000, 800_000,
+    1_000_000,
+];
+
+/// Set bus bit rate command, used in control mode.
+///
+/// Only the [`STANDARD_BAUD_RATES`] are accepted.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct SetBaud {
+    /// Bit rate in bits per second.
+    pub rate: u32,
+}
+
+impl Display for SetBaud {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< setbaud {} >", self.rate)
+    }
+}
+
+#[inline]
+fn set_baud(input: &str) -> IResult<'_, SetBaud> {
+    let (rest, rate) = keyword(
+        "< setbaud ",
+        "malformed setbaud command",
+        terminated(map_res(digit1, u32::from_str), tag(" >")),
+    )(input)?;
+
+    if !STANDARD_BAUD_RATES.contains(&rate) {
+        let mut err = ParseError::new(input, ErrorKind::Verify);
+        err.context = Some("non-standard baud rate");
+        return Err(nom::Err::Failure(err));
+    }
+
+    Ok((rest, SetBaud { rate }))
+}
+
+/// Send a single CAN XL frame command.
+///
+/// `< xlsend ID PRIORITY VC LEN DATA >`, where the acceptance field `ID` and
+/// the data bytes are hex and the rest are decimal.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct XlSend {
+    /// Acceptance field.
+    pub id: u32,
+    /// Priority.
+    pub priority: u8,
+    /// Virtual CAN network id.
+    pub vc: u8,
+    /// Payload length in bytes.
+    pub dlc: u16,
+    /// Payload.
+    pub data: XlData,
+}
+
+impl Display for XlSend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "< xlsend {:08X} {} {} {} ",
+            self.id, self.priority, self.vc, self.dlc
+        )?;
+
+        for byte in self.data.iter() {
+            write!(f, "{:02X} ", byte)?;
+        }
+
+        write!(f, ">")
+    }
+}
+
+#[inline]
+fn xl_send(input: &str) -> IResult<'_, XlSend> {
+    let (input, (id, priority, vc, dlc, data)) = keyword(
+        "< xlsend ",
+        "malformed xlsend command",
+        terminated(
+            verify(
+                tuple((
+                    terminated(
+                        map_res(hex_digit1, |v| u32::from_str_radix(v, 16)),
+                        char(' '),
+                    ),
+                    terminated(map_res(digit1, u8::from_str), char(' ')),
+                    terminated(map_res(digit1, u8::from_str), char(' ')),
+                    terminated(map_res(digit1, u16::from_str), char(' ')),
+                    map_res(
+                        take_while(|c: char| c.is_ascii_hexdigit() || c == ' '),
+                        parse_hex_bytes::<MAX_XL_DATA_LEN>,
+                    ),
+                )),
+                |(_, _, _, dlc, data)| *dlc != 0 && *dlc as usize == data.len(),
+            ),
+            char('>'),
+        ),
+    )(input)?;
+
+    Ok((
+        input,
+        XlSend {
+            id,
+            priority,
+            vc,
+            dlc,
+            data: xl_data(data),
+        },
+    ))
+}
+
+/// Get bus state command.
+///
+/// The server replies with a [`BusState`] response, e.g.
+/// `< state ERROR_ACTIVE >`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct GetState;
+
+impl Display for GetState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< get_state >")
+    }
+}
+
+#[inline]
+fn get_state(input: &str) -> IResult<'_, GetState> {
+    let (input, _) = tag("< get_state >")(input)?;
+
+    Ok((input, GetState))
+}
+
+/// Put the bus transceiver to sleep command.
+///
+/// Used when socketcand proxies a bus with a sleep mode, such as LIN.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Sleep;
+
+impl Display for Sleep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< sleep >")
+    }
+}
+
+#[inline]
+fn sleep(input: &str) -> IResult<'_, Sleep> {
+    let (input, _) = tag("< sleep >")(input)?;
+
+    Ok((input, Sleep))
+}
+
+/// Wake the bus transceiver up after a [`Sleep`] command.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Wakeup;
+
+impl Display for Wakeup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< wakeup >")
+    }
+}
+
+#[inline]
+fn wakeup(input: &str) -> IResult<'_, Wakeup> {
+    let (input, _) = tag("< wakeup >")(input)?;
+
+    Ok((input, Wakeup))
+}
+
+/// Get version command, used in control mode.
+///
+/// The server replies with a [`Version`] response, e.g. `< version 1.2.0 >`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct GetVersion;
+
+impl Display for GetVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< get_version >")
+    }
+}
+
+#[inline]
+fn get_version(input: &str) -> IResult<'_, GetVersion> {
+    let (input, _) = tag("< get_version >")(input)?;
+
+    Ok((input, GetVersion))
+}
+
+/// CAN FD frame flags, encoded on the wire as a hex bitmask.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct FdFlags {
+    /// Bit rate switch, the data phase is sent at the higher bit rate.
+    pub brs: bool,
+    /// Error state indicator, set by error passive transmitters.
+    pub esi: bool,
+}
+
+impl FdFlags {
+    /// Bit rate switch bit, as in `linux/can.h`.
+    const BRS: u8 = 0x01;
+    /// Error state indicator bit, as in `linux/can.h`.
+    const ESI: u8 = 0x02;
+
+    /// Returns the flags as a bitmask.
+    pub fn bits(&self) -> u8 {
+        let mut bits = 0;
+
+        if self.brs {
+            bits |= Self::BRS;
+        }
+
+        if self.esi {
+            bits |= Self::ESI;
+        }
+
+        bits
+    }
+
+    /// Create flags from a bitmask, ignoring unknown bits.
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            brs: bits & Self::BRS != 0,
+            esi: bits & Self::ESI != 0,
+        }
+    }
+}
+
+/// Send a single CAN FD frame command.
+///
+/// `< fdsend ID FLAGS LEN DATA >`, where `FLAGS` is the [`FdFlags`] bitmask
+/// in hex and `LEN` one of the CAN FD data lengths (0 to 8, 12, 16, 20, 24,
+/// 32, 48 or 64).
+#[derive(Debug, PartialEq, Clone)]
+pub struct FdSend {
+    /// CAN identifier.
+    pub id: Id,
+    /// CAN FD flags.
+    pub fd_flags: FdFlags,
+    /// Data length.
+    pub len: u8,
+    /// CAN data.
+    pub data: Vec<u8, MAX_FD_DATA_LEN>,
+}
+
+impl Display for FdSend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< fdsend ")?;
+        write_id(f, self.id)?;
+        write!(f, " {:X} {} ", self.fd_flags.bits(), self.len)?;
+
+        for byte in &self.data {
+            write!(f, "{:02X} ", byte)?;
+        }
+
+        write!(f, ">")
+    }
+}
+
+#[inline]
+fn fd_send(input: &str) -> IResult<'_, FdSend> {
+    let (input, (id, fd_flags, len, data)) = keyword(
+        "< fdsend ",
+        "malformed fdsend command",
+        terminated(
+            verify(
+                tuple((
+                    id,
+                    terminated(
+                        map(
+                            map_res(hex_digit1, |v| u8::from_str_radix(v, 16)),
+                            FdFlags::from_bits,
+                        ),
+                        char(' '),
+                    ),
+                    terminated(map_res(digit1, u8::from_str), char(' ')),
+                    map_res(
+                        take_while(|c: char| c.is_ascii_hexdigit() || c == ' '),
+                        parse_hex_bytes::<MAX_FD_DATA_LEN>,
+                    ),
+                )),
+                |(_, _, len, data)| {
+                    matches!(len, 0..=8 | 12 | 16 | 20 | 24 | 32 | 48 | 64)
+                        && *len as usize == data.len()
+                },
+            ),
+            char('>'),
+        ),
+    )(input)?;
+
+    Ok((
+        input,
+        FdSend {
+            id,
+            fd_flags,
+            len,
+            data,
+        },
+    ))
+}
+
+/// Subscribe to echoes of frames the server transmits with a CAN identifier
+/// (e.g. `< subscribe_tx 123 >`).
+///
+/// Each such frame is echoed back to the client as a [`TxEcho`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct SubscribeTx {
+    /// CAN identifier.
+    pub id: Id,
+}
+
+impl Display for SubscribeTx {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< subscribe_tx ")?;
+        write_id(f, self.id)?;
+        write!(f, " >")
+    }
+}
+
+#[inline]
+fn subscribe_tx(input: &str) -> IResult<'_, SubscribeTx> {
+    let (input, id) = keyword(
+        "< subscribe_tx ",
+        "malformed subscribe_tx command",
+        terminated(id, char('>')),
+    )(input)?;
+
+    Ok((input, SubscribeTx { id }))
+}
+
+/// Heartbeat configuration (e.g. `< heartbeat 5000 >`).
+///
+/// The client promises to send a command at least once per interval, e.g.
+/// a [`Nop`], so a silent connection can be detected. An interval of zero
+/// disables the heartbeat.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct HeartbeatConfig {
+    /// Heartbeat interval, sent in milliseconds.
+    pub interval: Duration,
+}
+
+impl Display for HeartbeatConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< heartbeat {} >", self.interval.as_millis())
+    }
+}
+
+#[inline]
+fn heartbeat_config(input: &str) -> IResult<'_, HeartbeatConfig> {
+    let (input, millis) = keyword(
+        "< heartbeat ",
+        "malformed heartbeat command",
+        terminated(map_res(digit1, u64::from_str), tag(" >")),
+    )(input)?;
+
+    Ok((
+        input,
+        HeartbeatConfig {
+            interval: Duration::from_millis(millis),
+        },
+    ))
+}
+
+/// Get bit rate command.
+///
+/// The server replies with a [`Bitrate`] response, e.g. `< bitrate 500000 >`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct GetBitrate;
+
+impl Display for GetBitrate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< getbitrate >")
+    }
+}
+
+#[inline]
+fn get_bitrate(input: &str) -> IResult<'_, GetBitrate> {
+    let (input, _) = tag("< getbitrate >")(input)?;
+
+    Ok((input, GetBitrate))
+}
+
+/// Get last error command.
+///
+/// The server replies with a [`LastErrorCode`] response, e.g.
+/// `< last_error 0 >`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct LastError;
+
+impl Display for LastError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< lasterror >")
+    }
+}
+
+#[inline]
+fn last_error(input: &str) -> IResult<'_, LastError> {
+    let (input, _) = tag("< lasterror >")(input)?;
+
+    Ok((input, LastError))
+}
+
+/// Maximum node name length in an [`Announce`] command.
+pub const MAX_ANNOUNCE_NAME_LEN: usize = 32;
+
+/// Announce command, sent between peers of a mesh of socketcand gateways.
+///
+/// # Example
+/// ```rust
+/// use socketcand::wire::{command, Command};
+///
+/// let (_, cmd) = command("< announce node1 192.168.1.10 29536 >").unwrap();
+///
+/// if let Command::Announce(announce) = cmd {
+///     assert_eq!(announce.name, "node1");
+///     assert_eq!(announce.port, 29536);
+/// }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Announce {
+    /// Name of the announcing node.
+    pub name: String<MAX_ANNOUNCE_NAME_LEN>,
+    /// Address the node accepts connections on.
+    pub ip: IpAddr,
+    /// Port the node accepts connections on.
+    pub port: u16,
+}
+
+impl Display for Announce {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< announce {} {} {} >", self.name, self.ip, self.port)
+    }
+}
+
+// `core::net` addresses only implement `defmt::Format` behind a defmt feature
+#[cfg(feature = "defmt-03")]
+impl defmt::Format for Announce {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "Announce {{ name: {=str}, ip: {}, port: {=u16} }}",
+            self.name.as_str(),
+            defmt::Display2Format(&self.ip),
+            self.port
+        )
+    }
+}
+
+#[inline]
+fn announce(input: &str) -> IResult<'_, Announce> {
+    let (input, (name, ip, port)) = keyword(
+        "< announce ",
+        "malformed announce command",
+        terminated(
+            tuple((
+                terminated(
+                    map_res(
+                        take_till1(|c: char| c == ' ' || c == '>'),
+                        String::try_from,
+                    ),
+                    char(' '),
+                ),
+                terminated(
+                    map_res(take_till1(|c: char| c == ' '), IpAddr::from_str),
+                    char(' '),
+                ),
+                map_res(digit1, u16::from_str),
+            )),
+            tag(" >"),
+        ),
+    )(input)?;
+
+    Ok((input, Announce { name, ip, port }))
+}
+
+/// Request a single delivery of the last frame received with a CAN
+/// identifier (e.g. `< request_frames 123 >`).
+///
+/// The server replies with the frame as a [`Frame`] response.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RequestFrames {
+    /// CAN identifier.
+    pub id: Id,
+}
+
+impl Display for RequestFrames {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< request_frames ")?;
+        write_id(f, self.id)?;
+        write!(f, " >")
+    }
+}
+
+#[inline]
+fn request_frames(input: &str) -> IResult<'_, RequestFrames> {
+    let (input, id) = keyword(
+        "< request_frames ",
+        "malformed request_frames command",
+        terminated(id, char('>')),
+    )(input)?;
+
+    Ok((input, RequestFrames { id }))
+}
+
+/// Command.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Command {
+    /// Open command.
+    Open(Open),
+    /// Add command.
+    Add(Add),
+    /// Update command.
+    Update(Update),
+    /// Delete command.
+    Delete(Delete),
+    /// Send command.
+    Send(Send),
+    /// Filter command.
+    Filter(Filter),
+    /// Echo command.
+    Echo(Echo),
+    /// Raw mode command.
+    RawMode(RawMode),
+    /// Broadcast mode command.
+    BroadcastMode(BroadcastMode),
+    /// Control mode command.
+    ControlMode(ControlMode),
+    /// ISO-TP mode command.
+    IsoTpMode(IsoTpMode),
+    /// Statistics command.
+    Statistics(Statistics),
+    /// Authentication command.
+    Auth(Auth),
+    /// List jobs command.
+    ListJobs(ListJobs),
+    /// Get busses command.
+    GetBusses(GetBusses),
+    /// BCM transmit setup command.
+    BcmTxSetup(BcmTxSetup),
+    /// BCM receive setup command.
+    BcmRxSetup(BcmRxSetup),
+    /// ISO-TP configuration command.
+    IsoTpConf(IsoTpConf),
+    /// No-operation command.
+    Nop(Nop),
+    /// Delete range command.
+    DeleteRange(DeleteRange),
+    /// Add count command.
+    AddCount(AddCount),
+    /// Set bus bit rate.
+    SetBaud(SetBaud),
+    /// Send a single CAN XL frame.
+    XlSend(XlSend),
+    /// Get bus state.
+    GetState(GetState),
+    /// Put the bus to sleep.
+    Sleep(Sleep),
+    /// Wake the bus up.
+    Wakeup(Wakeup),
+    /// Get firmware or daemon version.
+    GetVersion(GetVersion),
+    /// Send a single CAN FD frame.
+    FdSend(FdSend),
+    /// `< subscribe_tx >` command.
+    SubscribeTx(SubscribeTx),
+    /// `< heartbeat >` command.
+    HeartbeatConfig(HeartbeatConfig),
+    /// `< getbitrate >` command.
+    GetBitrate(GetBitrate),
+    /// `< lasterror >` command.
+    LastError(LastError),
+    /// `< announce >` command.
+    Announce(Announce),
+    /// `< request_frames >` command.
+    RequestFrames(RequestFrames),
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Command::Open(cmd) => cmd.fmt(f),
+            Command::Add(cmd) => cmd.fmt(f),
+            Command::Update(cmd) => cmd.fmt(f),
+            Command::Delete(cmd) => cmd.fmt(f),
+            Command::Send(cmd) => cmd.fmt(f),
+            Command::Filter(cmd) => cmd.fmt(f),
+            Command::Echo(cmd) => cmd.fmt(f),
+            Command::RawMode(cmd) => cmd.fmt(f),
+            Command::BroadcastMode(cmd) => cmd.fmt(f),
+            Command::ControlMode(cmd) => cmd.fmt(f),
+            Command::IsoTpMode(cmd) => cmd.fmt(f),
+            Command::Statistics(cmd) => cmd.fmt(f),
+            Command::Auth(cmd) => cmd.fmt(f),
+            Command::ListJobs(cmd) => cmd.fmt(f),
+            Command::GetBusses(cmd) => cmd.fmt(f),
+            Command::BcmTxSetup(cmd) => cmd.fmt(f),
+            Command::BcmRxSetup(cmd) => cmd.fmt(f),
+            Command::IsoTpConf(cmd) => cmd.fmt(f),
+            Command::Nop(cmd) => cmd.fmt(f),
+            Command::DeleteRange(cmd) => cmd.fmt(f),
+            Command::AddCount(cmd) => cmd.fmt(f),
+            Command::SetBaud(cmd) => cmd.fmt(f),
+            Command::XlSend(cmd) => cmd.fmt(f),
+            Command::GetState(cmd) => cmd.fmt(f),
+            Command::Sleep(cmd) => cmd.fmt(f),
+            Command::Wakeup(cmd) => cmd.fmt(f),
+            Command::GetVersion(cmd) => cmd.fmt(f),
+            Command::FdSend(cmd) => cmd.fmt(f),
+            Command::SubscribeTx(cmd) => cmd.fmt(f),
+            Command::HeartbeatConfig(cmd) => cmd.fmt(f),
+            Command::GetBitrate(cmd) => cmd.fmt(f),
+            Command::LastError(cmd) => cmd.fmt(f),
+            Command::Announce(cmd) => cmd.fmt(f),
+            Command::RequestFrames(cmd) => cmd.fmt(f),
+        }
+    }
+}
+
+/// Direction a message travels in.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum Direction {
+    /// Sent by the client to the server.
+    ClientToServer,
+    /// Sent by the server to the client.
+    ServerToClient,
+    /// Sent in both directions.
+    Bidirectional,
+}
+
+impl Command {
+    /// Returns the direction this command is sent in.
+    pub fn direction(&self) -> Direction {
+        match self {
+            Command::Echo(_) => Direction::Bidirectional,
+            Command::Open(_)
+            | Command::Add(_)
+            | Command::Update(_)
+            | Command::Delete(_)
+            | Command::Send(_)
+            | Command::Filter(_)
+            | Command::RawMode(_)
+            | Command::BroadcastMode(_)
+            | Command::ControlMode(_)
+            | Command::IsoTpMode(_)
+            | Command::Statistics(_)
+            | Command::Auth(_)
+            | Command::ListJobs(_)
+            | Command::GetBusses(_)
+            | Command::BcmTxSetup(_)
+            | Command::BcmRxSetup(_)
+            | Command::IsoTpConf(_)
+            | Command::Nop(_)
+            | Command::DeleteRange(_)
+            | Command::AddCount(_)
+            | Command::SetBaud(_)
+            | Command::XlSend(_)
+            | Command::GetState(_)
+            | Command::Sleep(_)
+            | Command::Wakeup(_)
+            | Command::GetVersion(_)
+            | Command::FdSend(_)
+            | Command::SubscribeTx(_)
+            | Command::HeartbeatConfig(_)
+            | Command::GetBitrate(_)
+            | Command::LastError(_)
+            | Command::Announce(_)
+            | Command::RequestFrames(_) => Direction::ClientToServer,
+        }
+    }
+
+    /// Returns the kind of this command.
+    pub fn kind(&self) -> CommandKind {
+        match self {
+            Command::Open(_) => CommandKind::Open,
+            Command::Add(_) => CommandKind::Add,
+            Command::Update(_) => CommandKind::Update,
+            Command::Delete(_) => CommandKind::Delete,
+            Command::Send(_) => CommandKind::Send,
+            Command::Filter(_) => CommandKind::Filter,
+            Command::Echo(_) => CommandKind::Echo,
+            Command::RawMode(_) => CommandKind::RawMode,
+            Command::BroadcastMode(_) => CommandKind::BroadcastMode,
+            Command::ControlMode(_) => CommandKind::ControlMode,
+            Command::IsoTpMode(_) => CommandKind::IsoTpMode,
+            Command::Statistics(_) => CommandKind::Statistics,
+            Command::Auth(_) => CommandKind::Auth,
+            Command::ListJobs(_) => CommandKind::ListJobs,
+            Command::GetBusses(_) => CommandKind::GetBusses,
+            Command::BcmTxSetup(_) => CommandKind::BcmTxSetup,
+            Command::BcmRxSetup(_) => CommandKind::BcmRxSetup,
+            Command::IsoTpConf(_) => CommandKind::IsoTpConf,
+            Command::Nop(_) => CommandKind::Nop,
+            Command::DeleteRange(_) => CommandKind::DeleteRange,
+            Command::AddCount(_) => CommandKind::AddCount,
+            Command::SetBaud(_) => CommandKind::SetBaud,
+            Command::XlSend(_) => CommandKind::XlSend,
+            Command::GetState(_) => CommandKind::GetState,
+            Command::Sleep(_) => CommandKind::Sleep,
+            Command::Wakeup(_) => CommandKind::Wakeup,
+            Command::GetVersion(_) => CommandKind::GetVersion,
+            Command::FdSend(_) => CommandKind::FdSend,
+            Command::SubscribeTx(_) => CommandKind::SubscribeTx,
+            Command::HeartbeatConfig(_) => CommandKind::HeartbeatConfig,
+            Command::GetBitrate(_) => CommandKind::GetBitrate,
+            Command::LastError(_) => CommandKind::LastError,
+            Command::Announce(_) => CommandKind::Announce,
+            Command::RequestFrames(_) => CommandKind::RequestFrames,
+        }
+    }
+
+    /// Returns `true` if a client may send this command.
+    pub fn is_client_to_server(&self) -> bool {
+        self.direction() != Direction::ServerToClient
+    }
+
+    /// Returns `true` if a server may send this command.
+    pub fn is_server_to_client(&self) -> bool {
+        self.direction() != Direction::ClientToServer
+    }
+}
+
+/// Kind of a [`Command`], without its arguments.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum CommandKind {
+    /// `< open >` command.
+    Open,
+    /// `< add >` command.
+    Add,
+    /// `< update >` command.
+    Update,
+    /// `< delete >` command.
+    Delete,
+    /// `< send >` command.
+    Send,
+    /// `< filter >` or `< filter_inv >` command.
+    Filter,
+    /// `< echo >` command.
+    Echo,
+    /// `< rawmode >` command.
+    RawMode,
+    /// `< bcmode >` command.
+    BroadcastMode,
+    /// `< controlmode >` command.
+    ControlMode,
+    /// `< isotpmode >` command.
+    IsoTpMode,
+    /// `< statistics >` command.
+    Statistics,
+    /// `< auth >` command.
+    Auth,
+    /// `< list_jobs >` command.
+    ListJobs,
+    /// `< get_busses >` command.
+    GetBusses,
+    /// `< tx_setup >` command.
+    BcmTxSetup,
+    /// `< rx_setup >` command.
+    BcmRxSetup,
+    /// `< isotpconf >` command.
+    IsoTpConf,
+    /// `< nop >` command.
+    Nop,
+    /// `< delete_range >` command.
+    DeleteRange,
+    /// `< add_count >` command.
+    AddCount,
+    /// `< setbaud >` command.
+    SetBaud,
+    /// `< xlsend >` command.
+    XlSend,
+    /// `< get_state >` command.
+    GetState,
+    /// `< sleep >` command.
+    Sleep,
+    /// `< wakeup >` command.
+    Wakeup,
+    /// `< get_version >` command.
+    GetVersion,
+    /// `< fdsend >` command.
+    FdSend,
+    /// `< subscribe_tx >` command.
+    SubscribeTx,
+    /// `< heartbeat >` command.
+    HeartbeatConfig,
+    /// `< getbitrate >` command.
+    GetBitrate,
+    /// `< lasterror >` command.
+    LastError,
+    /// `< announce >` command.
+    Announce,
+    /// `< request_frames >` command.
+    RequestFrames,
+}
+
+/// Classify a command by its keyword without parsing its arguments.
+///
+/// Returns `None` if the input does not start with a known command keyword.
+///
+/// # Example
+/// ```rust
+/// use socketcand::wire::{peek_command_kind, CommandKind};
+///
+/// assert_eq!(peek_command_kind("< send 123 0 >"), Some(CommandKind::Send));
+/// assert_eq!(peek_command_kind("< bogus >"), None);
+/// ```
+#[inline]
+pub fn peek_command_kind(input: &str) -> Option<CommandKind> {
+    let rest = input.strip_prefix("< ")?;
+    let end = rest.find([' ', '>']).unwrap_or(rest.len());
+
+    let kind = match &rest[..end] {
+        "open" => CommandKind::Open,
+        "add" => CommandKind::Add,
+        "update" => CommandKind::Update,
+        "delete" => CommandKind::Delete,
+        "send" => CommandKind::Send,
+        "filter" | "filter_inv" => CommandKind::Filter,
+        "echo" => CommandKind::Echo,
+        "rawmode" => CommandKind::RawMode,
+        "bcmode" => CommandKind::BroadcastMode,
+        "controlmode" => CommandKind::ControlMode,
+        "isotpmode" => CommandKind::IsoTpMode,
+        "statistics" => CommandKind::Statistics,
+        "auth" => CommandKind::Auth,
+        "list_jobs" => CommandKind::ListJobs,
+        "get_busses" => CommandKind::GetBusses,
+        "tx_setup" => CommandKind::BcmTxSetup,
+        "rx_setup" => CommandKind::BcmRxSetup,
+        "isotpconf" => CommandKind::IsoTpConf,
+        "nop" => CommandKind::Nop,
+        "delete_range" => CommandKind::DeleteRange,
+        "add_count" => CommandKind::AddCount,
+        "setbaud" => CommandKind::SetBaud,
+        "xlsend" => CommandKind::XlSend,
+        "get_state" => CommandKind::GetState,
+        "sleep" => CommandKind::Sleep,
+        "wakeup" => CommandKind::Wakeup,
+        "get_version" => CommandKind::GetVersion,
+        "fdsend" => CommandKind::FdSend,
+        "subscribe_tx" => CommandKind::SubscribeTx,
+        "heartbeat" => CommandKind::HeartbeatConfig,
+        "getbitrate" => CommandKind::GetBitrate,
+        "lasterror" => CommandKind::LastError,
+        "announce" => CommandKind::Announce,
+        "request_frames" => CommandKind::RequestFrames,
+        _ => return None,
+    };
+
+    Some(kind)
+}
+
+/// Parse a socketcand command.
+#[inline]
+pub fn command(input: &str) -> IResult<'_, Command> {
+    // some clients separate commands with whitespace or line endings
+    delimited(
+        multispace0,
+        // split in two as `alt` takes at most 21 parsers
+        alt((
+            alt((
+                map(open, Command::Open),
+                map(add, Command::Add),
+                map(update, Command::Update),
+                map(delete, Command::Delete),
+                map(send, Command::Send),
+                map(filter, Command::Filter),
+                map(echo, Command::Echo),
+                map(raw_mode, Command::RawMode),
+                map(broadcast_mode, Command::BroadcastMode),
+                map(control_mode, Command::ControlMode),
+                map(iso_tp_mode, Command::IsoTpMode),
+                map(statistics, Command::Statistics),
+                map(auth, Command::Auth),
+                map(list_jobs, Command::ListJobs),
+            )),
+            alt((
+                map(get_busses, Command::GetBusses),
+                map(bcm_tx_setup, Command::BcmTxSetup),
+                map(bcm_rx_setup, Command::BcmRxSetup),
+                map(iso_tp_conf, Command::IsoTpConf),
+                map(nop, Command::Nop),
+                map(delete_range, Command::DeleteRange),
+                map(add_count, Command::AddCount),
+                map(set_baud, Command::SetBaud),
+                map(xl_send, Command::XlSend),
+                map(get_state, Command::GetState),
+                map(sleep, Command::Sleep),
+                map(wakeup, Command::Wakeup),
+                map(get_version, Command::GetVersion),
+                map(fd_send, Command::FdSend),
+                map(subscribe_tx, Command::SubscribeTx),
+                map(heartbeat_config, Command::HeartbeatConfig),
+                map(get_bitrate, Command::GetBitrate),
+                map(last_error, Command::LastError),
+                map(announce, Command::Announce),
+                map(request_frames, Command::RequestFrames),
+            )),
+        )),
+        multispace0,
+    )(input)
+}
+
+/// Maximum number of commands in a compound command.
+const MAX_COMPOUND_COMMANDS: usize = 8;
+
+/// Parse a compound command, where several commands are batched in one
+/// block separated by semicolons (e.g. `< open can0; rawmode >`).
+///
+/// A single command parses as a compound command with one entry.
+///
+/// # Example
+/// ```rust
+/// use socketcand::wire::{compound_command, Command};
+///
+/// let (_, commands) = compound_command("< open can0; rawmode >").unwrap();
+///
+/// assert!(matches!(commands[0], Command::Open(_)));
+/// assert!(matches!(commands[1], Command::RawMode(_)));
+/// ```
+#[inline]
+pub fn compound_command(
+    input: &str,
+) -> IResult<'_, Vec<Command, MAX_COMPOUND_COMMANDS>> {
+    let (input, body) = terminated(
+        preceded(tag("< "), take_till1(|c: char| c == '>')),
+        terminated(char('>'), opt(line_ending)),
+    )(input)?;
+
+    let mut commands = Vec::new();
+
+    for part in body.split(';') {
+        let part = part.trim();
+        let fail = || {
+            let mut err = ParseError::new(part, ErrorKind::Verify);
+            err.context = Some("malformed compound command");
+            nom::Err::Failure(err)
+        };
+
+        // each part is parsed as a standalone command
+        let mut single = String::<128>::new();
+        write!(&mut single, "< {} >", part).map_err(|_| fail())?;
+
+        match command(&single) {
+            Ok(("", cmd)) => commands.push(cmd).map_err(|_| fail())?,
+            _ => return Err(fail()),
+        }
+    }
+
+    Ok((input, commands))
+}
+
+/// Cyclic job entry, sent in response to [`ListJobs`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct Job {
+    /// Interval.
+    pub interval: Duration,
+    /// CAN identifier.
+    pub id: Id,
+    /// CAN data length code.
+    pub dlc: u8,
+    /// CAN data.
+    pub data: Vec<u8, MAX_DATA_LEN>,
+}
+
+impl From<Add> for Job {
+    fn from(add: Add) -> Self {
+        Self {
+            interval: add.interval,
+            id: add.id,
+            dlc: add.dlc,
+            data: add.data,
+        }
+    }
+}
+
+impl Display for Job {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< job ")?;
+        write_id(f, self.id)?;
+        write!(
+            f,
+            " {} {} {} ",
+            self.interval.as_secs(),
+            self.interval.subsec_micros(),
+            self.dlc
+        )?;
+
+        for byte in &self.data {
+            write!(f, "{:02X} ", byte)?;
+        }
+
+        write!(f, ">")
+    }
+}
+
+#[inline]
+fn job(input: &str) -> IResult<'_, Job> {
+    let (input, (id, secs, micros, dlc, data)) = keyword(
+        "< job ",
+        "malformed job response",
+        terminated(
+            tuple((
+                id,
+                terminated(map_res(digit1, u64::from_str), char(' ')),
+                terminated(map_res(digit1, u64::from_str), char(' ')),
+                terminated(map_res(digit1, u8::from_str), char(' ')),
+                map_res(
+                    take_while(|c: char| c.is_ascii_hexdigit() || c == ' '),
+                    parse_hex_bytes,
+                ),
+            )),
+            char('>'),
+        ),
+    )(input)?;
+
+    let interval = seconds_micros(input, secs, micros)?;
+
+    Ok((
+        input,
+        Job {
+            interval,
+            id,
+            dlc,
+            data,
+        },
+    ))
+}
+
+/// Welcome message, sent by the server when a client connects.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Hi;
+
+impl Display for Hi {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< hi >")
+    }
+}
+
+#[inline]
+fn hi(input: &str) -> IResult<'_, Hi> {
+    let (input, _) = tag("< hi >")(input)?;
+
+    Ok((input, Hi))
+}
+
+/// Command acknowledgement, `< ok >`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Ack;
+
+impl Display for Ack {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< ok >")
+    }
+}
+
+#[inline]
+fn ack(input: &str) -> IResult<'_, Ack> {
+    let (input, _) = tag("< ok >")(input)?;
+
+    Ok((input, Ack))
+}
+
+/// Error response, with an optional reason (e.g. `< error not_found >`).
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Error {
+    /// Reason for the error.
+    pub reason: Option<String<MAX_ERROR_REASON_LEN>>,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match &self.reason {
+            Some(reason) => write!(f, "< error {} >", reason),
+            None => write!(f, "< error >"),
+        }
+    }
+}
+
+#[inline]
+fn error(input: &str) -> IResult<'_, Error> {
+    let (input, reason) = alt((
+        map(tag("< error >"), |_| None),
+        map(
+            keyword(
+                "< error ",
+                "malformed error response",
+                terminated(
+                    map_res(take_till1(|c: char| c == '>'), |reason: &str| {
+                        String::try_from(reason.trim_end())
+                    }),
+                    char('>'),
+                ),
+            ),
+            Some,
+        ),
+    ))(input)?;
+
+    Ok((input, Error { reason }))
+}
+
+/// End of job list marker, sent after the last [`Job`].
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct EndList;
+
+impl Display for EndList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< end_list >")
+    }
+}
+
+#[inline]
+fn end_list(input: &str) -> IResult<'_, EndList> {
+    let (input, _) = tag("< end_list >")(input)?;
+
+    Ok((input, EndList))
+}
+
+/// Received CAN frame.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Frame {
+    /// CAN identifier.
+    pub id: Id,
+    /// Time the frame was received.
+    pub timestamp: Duration,
+    /// CAN data.
+    pub data: Vec<u8, MAX_DATA_LEN>,
+}
+
+impl embedded_can::Frame for Frame {
+    fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+        Some(Self {
+            id: id.into(),
+            timestamp: Duration::ZERO,
+            data: Vec::from_slice(data).ok()?,
+        })
+    }
+
+    fn new_remote(_id: impl Into<Id>, _dlc: usize) -> Option<Self> {
+        // remote frames are not forwarded by socketcand
+        None
+    }
+
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    fn dlc(&self) -> usize {
+        self.data.len()
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn is_extended(&self) -> bool {
+        match self.id {
+            Id::Extended(_) => true,
+            Id::Standard(_) => false,
+        }
+    }
+
+    fn is_remote_frame(&self) -> bool {
+        false
+    }
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        format_frame(f, self.id, self.timestamp, &self.data)
+    }
+}
+
+/// Controller error frame.
+///
+/// `< error_frame SECS.USECS CLASS DATA >`, where `CLASS` is the error class
+/// as a hex byte and `DATA` the eight error data bytes as contiguous hex
+/// pairs, following the layout of Linux SocketCAN error frames.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct FrameError {
+    /// Time the error occurred.
+    pub timestamp: Duration,
+    /// Error class.
+    pub error_class: u8,
+    /// Error details, see `linux/can/error.h`.
+    pub error_data: [u8; MAX_DATA_LEN],
+}
+
+impl Display for FrameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "< error_frame {}.{:06} {:02X} ",
+            self.timestamp.as_secs(),
+            self.timestamp.subsec_micros(),
+            self.error_class
+        )?;
+
+        for byte in self.error_data {
+            write!(f, "{:02X}", byte)?;
+        }
+
+        write!(f, " >")
+    }
+}
+
+#[inline]
+fn frame_error(input: &str) -> IResult<'_, FrameError> {
+    let (input, (secs, micros, error_class, error_data)) = keyword(
+        "< error_frame ",
+        "malformed error_frame response",
+        terminated(
+            tuple((
+                terminated(map_res(digit1, u64::from_str), char('.')),
+                terminated(map_res(digit1, u64::from_str), char(' ')),
+                terminated(
+                    map_res(hex_digit1, |v| u8::from_str_radix(v, 16)),
+                    char(' '),
+                ),
+                map_res(hex_digit1, |hex| {
+                    hex_pairs::<MAX_DATA_LEN>(hex)?
+                        .into_array()
+                        .map_err(|_| "Expected eight bytes.")
+                }),
+            )),
+            tag(" >"),
+        ),
+    )(input)?;
+
+    let timestamp = seconds_micros(input, secs, micros)?;
+
+    Ok((
+        input,
+        FrameError {
+            timestamp,
+            error_class,
+            error_data,
+        },
+    ))
+}
+
+/// Parse contiguous hex byte pairs (e.g. `11AB`).
+fn hex_pairs<const N: usize>(hex: &str) -> Result<Vec<u8, N>, &'static str> {
+    if !hex.len().is_multiple_of(2) {
+        return Err("Odd number of hex digits.");
+    }
+
+    let mut data = Vec::new();
+
+    for pair in hex.as_bytes().chunks(2) {
+        let byte = core::str::from_utf8(pair)
+            .ok()
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            .ok_or("Invalid hex byte.")?;
+        data.push(byte).map_err(|_| "Too many data bytes.")?;
+    }
+
+    Ok(data)
+}
+
+#[inline]
+fn frame(input: &str) -> IResult<'_, Frame> {
+    let (input, (id, secs, micros, data)) = keyword(
+        "< frame ",
+        "malformed frame response",
+        terminated(
+            tuple((
+                id,
+                terminated(map_res(digit1, u64::from_str), char('.')),
+                terminated(map_res(digit1, u64::from_str), char(' ')),
+                map_res(take_while(|c: char| c.is_ascii_hexdigit()), hex_pairs),
+            )),
+            tag(" >"),
+        ),
+    )(input)?;
+
+    let timestamp = seconds_micros(input, secs, micros)?;
+
+    Ok((
+        input,
+        Frame {
+            id,
+            timestamp,
+            data,
+        },
+    ))
+}
+
+/// Received CAN XL frame.
+///
+/// `< xlframe ID PRIORITY VC SECS.USECS DATA >`, formatted like [`Frame`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct XlFrame {
+    /// Acceptance field.
+    pub id: u32,
+    /// Priority.
+    pub priority: u8,
+    /// Virtual CAN network id.
+    pub vc: u8,
+    /// Time the frame was received.
+    pub timestamp: Duration,
+    /// Payload.
+    pub data: XlData,
+}
+
+impl Display for XlFrame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "< xlframe {:08X} {} {} {}.{:06} ",
+            self.id,
+            self.priority,
+            self.vc,
+            self.timestamp.as_secs(),
+            self.timestamp.subsec_micros()
+        )?;
+
+        for byte in self.data.iter() {
+            write!(f, "{:02X}", byte)?;
+        }
+
+        write!(f, " >")
+    }
+}
+
+#[inline]
+fn xl_frame(input: &str) -> IResult<'_, XlFrame> {
+    let (input, (id, priority, vc, secs, micros, data)) = keyword(
+        "< xlframe ",
+        "malformed xlframe response",
+        terminated(
+            tuple((
+                terminated(
+                    map_res(hex_digit1, |v| u32::from_str_radix(v, 16)),
+                    char(' '),
+                ),
+                terminated(map_res(digit1, u8::from_str), char(' ')),
+                terminated(map_res(digit1, u8::from_str), char(' ')),
+                terminated(map_res(digit1, u64::from_str), char('.')),
+                terminated(map_res(digit1, u64::from_str), char(' ')),
+                map_res(take_while(|c: char| c.is_ascii_hexdigit()), hex_pairs),
+            )),
+            tag(" >"),
+        ),
+    )(input)?;
+
+    let timestamp = seconds_micros(input, secs, micros)?;
+
+    Ok((
+        input,
+        XlFrame {
+            id,
+            priority,
+            vc,
+            timestamp,
+            data: xl_data(data),
+        },
+    ))
+}
+
+/// CAN controller error state.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum BusStateKind {
+    /// Error active, the normal operating state.
+    ErrorActive,
+    /// Error counters have passed the warning limit.
+    ErrorWarning,
+    /// Error passive, the controller no longer sends active error frames.
+    ErrorPassive,
+    /// Bus off, the controller has stopped taking part in bus traffic.
+    BusOff,
+}
+
+impl Display for BusStateKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let state = match self {
+            BusStateKind::ErrorActive => "ERROR_ACTIVE",
+            BusStateKind::ErrorWarning => "ERROR_WARNING",
+            BusStateKind::ErrorPassive => "ERROR_PASSIVE",
+            BusStateKind::BusOff => "BUS_OFF",
+        };
+
+        f.write_str(state)
+    }
+}
+
+/// Bus state, sent in reply to [`GetState`].
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct BusState {
+    /// Controller error state.
+    pub state: BusStateKind,
+}
+
+impl Display for BusState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< state {} >", self.state)
+    }
+}
+
+#[inline]
+fn bus_state(input: &str) -> IResult<'_, BusState> {
+    let (input, state) = keyword(
+        "< state ",
+        "malformed state response",
+        terminated(
+            alt((
+                map(tag("ERROR_ACTIVE"), |_| BusStateKind::ErrorActive),
+                map(tag("ERROR_WARNING"), |_| BusStateKind::ErrorWarning),
+                map(tag("ERROR_PASSIVE"), |_| BusStateKind::ErrorPassive),
+                map(tag("BUS_OFF"), |_| BusStateKind::BusOff),
+            )),
+            tag(" >"),
+        ),
+    )(input)?;
+
+    Ok((input, BusState { state }))
+}
+
+/// Bit rate, sent in reply to [`GetBitrate`].
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Bitrate {
+    /// Bit rate in bits per second.
+    pub rate: u32,
+}
+
+impl Display for Bitrate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< bitrate {} >", self.rate)
+    }
+}
+
+#[inline]
+fn bitrate(input: &str) -> IResult<'_, Bitrate> {
+    let (input, rate) = keyword(
+        "< bitrate ",
+        "malformed bitrate response",
+        terminated(map_res(digit1, u32::from_str), tag(" >")),
+    )(input)?;
+
+    Ok((input, Bitrate { rate }))
+}
+
+/// Last CAN controller error, sent in reply to [`LastError`].
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct LastErrorCode {
+    /// Controller specific error code, zero if there was no error.
+    pub code: u32,
+}
+
+impl Display for LastErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< last_error {} >", self.code)
+    }
+}
+
+#[inline]
+fn last_error_code(input: &str) -> IResult<'_, LastErrorCode> {
+    let (input, code) = keyword(
+        "< last_error ",
+        "malformed last_error response",
+        terminated(map_res(digit1, u32::from_str), tag(" >")),
+    )(input)?;
+
+    Ok((input, LastErrorCode { code }))
+}
+
+/// Maximum version string length in a [`Version`] response.
+pub const MAX_VERSION_LEN: usize = 32;
+
+/// Version, sent in reply to [`GetVersion`].
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Version {
+    /// Version string, e.g. `1.2.0`.
+    pub version: String<MAX_VERSION_LEN>,
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< version {} >", self.version)
+    }
+}
+
+#[inline]
+fn version(input: &str) -> IResult<'_, Version> {
+    let (input, version) = keyword(
+        "< version ",
+        "malformed version response",
+        terminated(
+            map_res(
+                take_till1(|c: char| c == ' ' || c == '>'),
+                String::try_from,
+            ),
+            tag(" >"),
+        ),
+    )(input)?;
+
+    Ok((input, Version { version }))
+}
+
+/// Echo of a frame transmitted by the server, sent to clients subscribed
+/// with [`SubscribeTx`] (e.g. `< txecho 123 2 01 02 >`).
+#[derive(Debug, PartialEq, Clone)]
+pub struct TxEcho {
+    /// CAN identifier.
+    pub id: Id,
+    /// CAN data length code.
+    pub dlc: u8,
+    /// CAN data.
+    pub data: Vec<u8, MAX_DATA_LEN>,
+}
+
+impl Display for TxEcho {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< txecho ")?;
+        write_id(f, self.id)?;
+        write!(f, " {} ", self.dlc)?;
+
+        for byte in &self.data {
+            write!(f, "{:02X} ", byte)?;
+        }
+
+        write!(f, ">")
+    }
+}
+
+#[inline]
+fn tx_echo(input: &str) -> IResult<'_, TxEcho> {
+    let (input, (id, dlc, data)) = keyword(
+        "< txecho ",
+        "malformed txecho response",
+        terminated(
+            verify(
+                tuple((
+                    id,
+                    terminated(map_res(digit1, u8::from_str), char(' ')),
+                    map_res(
+                        take_while(|c: char| c.is_ascii_hexdigit() || c == ' '),
+                        parse_hex_bytes::<MAX_DATA_LEN>,
+                    ),
+                )),
+                |(_, dlc, data)| *dlc as usize == data.len(),
+            ),
+            char('>'),
+        ),
+    )(input)?;
+
+    Ok((input, TxEcho { id, dlc, data }))
+}
+
+/// Negative acknowledgement, rejecting a command for the given reason (e.g.
+/// `< nack reason="not_found" >`).
+///
+/// Unlike [`Error`], which reports commands that could not be processed, a
+/// nack reports a valid command the server refused.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Nack {
+    /// Reason the command was rejected.
+    pub reason: String<MAX_ERROR_REASON_LEN>,
+}
+
+impl Display for Nack {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "< nack reason=\"{}\" >", self.reason)
+    }
+}
+
+#[inline]
+fn nack(input: &str) -> IResult<'_, Nack> {
+    let (input, reason) = keyword(
+        "< nack ",
+        "malformed nack response",
+        delimited(
+            tag("reason=\""),
+            map_res(take_till1(|c: char| c == '"'), String::try_from),
+            tag("\" >"),
+        ),
+    )(input)?;
+
+    Ok((input, Nack { reason }))
+}
+
+/// Bus statistics, sent periodically after a [`Statistics`] command.
+#[derive(Debug, Default, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct BusStats {
+    /// Frames transmitted successfully.
+    pub tx_ok: u32,
+    /// Frames received successfully.
+    pub rx_ok: u32,
+    /// Transmit errors.
+    pub tx_err: u32,
+    /// Receive errors.
+    pub rx_err: u32,
+}
+
+impl Display for BusStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "< stat {} {} {} {} >",
+            self.tx_ok, self.rx_ok, self.tx_err, self.rx_err
+        )
+    }
+}
+
+#[inline]
+fn bus_stats(input: &str) -> IResult<'_, BusStats> {
+    let (input, (tx_ok, rx_ok, tx_err, rx_err)) = keyword(
+        "< stat ",
+        "malformed stat response",
+        terminated(
+            tuple((
+                terminated(map_res(digit1, u32::from_str), char(' ')),
+                terminated(map_res(digit1, u32::from_str), char(' ')),
+                terminated(map_res(digit1, u32::from_str), char(' ')),
+                terminated(map_res(digit1, u32::from_str), char(' ')),
+            )),
+            char('>'),
+        ),
+    )(input)?;
+
+    Ok((
+        input,
+        BusStats {
+            tx_ok,
+            rx_ok,
+            tx_err,
+            rx_err,
+        },
+    ))
+}
+
+/// Controller statistics, sent periodically in control mode.
+#[derive(Debug, Default, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct ControlModeStats {
+    /// Frames transmitted successfully.
+    pub tx_ok: u32,
+    /// Transmit errors.
+    pub tx_err: u32,
+    /// Frames received successfully.
+    pub rx_ok: u32,
+    /// Receive errors.
+    pub rx_err: u32,
+    /// Bus errors.
+    pub bus_err: u32,
+}
+
+impl Display for ControlModeStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "< stats {} {} {} {} {} >",
+            self.tx_ok, self.tx_err, self.rx_ok, self.rx_err, self.bus_err
+        )
+    }
+}
+
+#[inline]
+fn control_mode_stats(input: &str) -> IResult<'_, ControlModeStats> {
+    let (input, (tx_ok, tx_err, rx_ok, rx_err, bus_err)) = keyword(
+        "< stats ",
+        "malformed stats response",
+        terminated(
+            tuple((
+                terminated(map_res(digit1, u32::from_str), char(' ')),
+                terminated(map_res(digit1, u32::from_str), char(' ')),
+                terminated(map_res(digit1, u32::from_str), char(' ')),
+                terminated(map_res(digit1, u32::from_str), char(' ')),
+                terminated(map_res(digit1, u32::from_str), char(' ')),
+            )),
+            char('>'),
+        ),
+    )(input)?;
+
+    Ok((
+        input,
+        ControlModeStats {
+            tx_ok,
+            tx_err,
+            rx_ok,
+            rx_err,
+            bus_err,
+        },
+    ))
+}
+
+/// Response sent from the server to the client.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Response {
+    /// Received CAN frame.
+    Frame(Frame),
+    /// Received CAN XL frame.
+    XlFrame(XlFrame),
+    /// Controller error frame.
+    FrameError(FrameError),
+    /// Job list entry.
+    Job(Job),
+    /// End of job list.
+    EndList(EndList),
+    /// Bus statistics.
+    BusStats(BusStats),
+    /// Controller statistics.
+    ControlModeStats(ControlModeStats),
+    /// Bus state.
+    BusState(BusState),
+    /// Version.
+    Version(Version),
+    /// Welcome message.
+    Hi(Hi),
+    /// Command acknowledgement.
+    Ok(Ack),
+    /// Error.
+    Error(Error),
+    /// Rejected command.
+    Nack(Nack),
+    /// Echo of a transmitted frame.
+    TxEcho(TxEcho),
+    /// Bit rate.
+    Bitrate(Bitrate),
+    /// Last controller error.
+    LastErrorCode(LastErrorCode),
+}
+
+/// Parse a socketcand response.
+#[inline]
+pub fn response(input: &str) -> IResult<'_, Response> {
+    alt((
+        map(frame, Response::Frame),
+        map(xl_frame, Response::XlFrame),
+        map(frame_error, Response::FrameError),
+        map(job, Response::Job),
+        map(end_list, Response::EndList),
+        map(bus_stats, Response::BusStats),
+        map(control_mode_stats, Response::ControlModeStats),
+        map(bus_state, Response::BusState),
+        map(version, Response::Version),
+        map(hi, Response::Hi),
+        map(ack, Response::Ok),
+        map(error, Response::Error),
+        map(nack, Response::Nack),
+        map(tx_echo, Response::TxEcho),
+        map(bitrate, Response::Bitrate),
+        map(last_error_code, Response::LastErrorCode),
+    ))(input)
+}
+
+/// Any socketcand message, either a command or a response.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Message {
+    /// Command.
+    Command(Command),
+    /// Response.
+    Response(Response),
+}
+
+/// Parse a socketcand message of either direction.
+///
+/// Responses are tried before commands.
+///
+/// # Example
+/// ```rust
+/// use socketcand::wire::{message, Message, Response};
+///
+/// let (_, result) = message("< ok >").unwrap();
+/// assert!(matches!(result, Message::Response(Response::Ok(_))));
+///
+/// let (_, result) = message("< open can0 >").unwrap();
+/// assert!(matches!(result, Message::Command(_)));
+/// ```
+#[inline]
+pub fn message(input: &str) -> IResult<'_, Message> {
+    alt((
+        map(response, Message::Response),
+        map(command, Message::Command),
+    ))(input)
+}
+
+/// Writes formatted messages into a borrowed byte buffer.
+///
+/// Implements [`Write`], so messages can be written with `write!` straight
+/// into a socket or DMA buffer without going through a [`String`]. Writing
+/// past the end of the buffer fails with [`core::fmt::Error`].
+///
+/// # Example
+/// ```
+/// use core::fmt::Write;
+/// use socketcand::wire::Builder;
+///
+/// let mut buf = [0; 32];
+/// let mut builder = Builder::new(&mut buf);
+/// write!(builder, "< send {:03X} {} {:02X} >", 0x123, 1, 0xAB).unwrap();
+/// assert_eq!(builder.as_bytes(), b"< send 123 1 AB >");
+/// ```
+#[derive(Debug)]
+pub struct Builder<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Builder<'a> {
+    /// Create an empty builder writing into `buf`.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// Bytes written so far.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.buf.get(..self.len).unwrap_or_default()
+    }
+
+    /// Number of bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if nothing has been written.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Discard everything written so far.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl Write for Builder<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len + s.len();
+        let dest = self.buf.get_mut(self.len..end).ok_or(core::fmt::Error)?;
+
+        dest.copy_from_slice(s.as_bytes());
+        self.len = end;
+
+        Ok(())
+    }
+}
+
+/// Format a `< frame ID SECS.USECS DATA >` message.
+///
+/// Data bytes are written as contiguous hex pairs, matching the output of
+/// the socketcand daemon.
+pub fn format_frame(
+    w: &mut impl Write,
+    id: Id,
+    timestamp: Duration,
+    data: &[u8],
+) -> core::fmt::Result {
+    write!(w, "< frame ")?;
+
+    match id {
+        Id::Standard(id) => write!(w, "{:03X} ", id.as_raw())?,
+        Id::Extended(id) => write!(w, "{:08X} ", id.as_raw())?,
+    }
+
+    write!(
+        w,
+        "{}.{:06} ",
+        timestamp.as_secs(),
+        timestamp.subsec_micros()
+    )?;
+
+    for byte in data {
+        write!(w, "{:02X}", byte)?;
+    }
+
+    write!(w, " >")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_can::Frame;
+
+    #[test]
+    fn parse_open() {
+        let (_, result) = command("< open vcan5 >").unwrap();
+        assert_eq!(
+            result,
+            Command::Open(Open {
+                index: 5,
+                bus_type: BusType::Virtual,
+                mode: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_open_large_index() {
+        let (_, result) = command("< open vcan1000 >").unwrap();
+        assert_eq!(
+            result,
+            Command::Open(Open {
+                index: 1000,
+                bus_type: BusType::Virtual,
+                mode: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_open_with_mode() {
+        let (_, result) = command("< open can0 raw >").unwrap();
+        assert_eq!(
+            result,
+            Command::Open(Open {
+                index: 0,
+                bus_type: BusType::Physical,
+                mode: Some(Mode::Raw),
+            })
+        );
+
+        assert!(command("< open can0 no_bus >").is_err());
+        assert!(command("< open can0 rawmode >").is_err());
+    }
+
+    #[test]
+    fn parse_add() {
+        let (_, result) =
+            command("< add 1 0 123 8 11 22 33 44 55 66 77 88 >").unwrap();
+        assert_eq!(
+            result,
+            Command::Add(Add {
+                interval: Duration::from_secs(1),
+                id: Id::Standard(StandardId::new(0x123).unwrap()),
+                dlc: 8,
+                data: Vec::from_slice(&[
+                    0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88
+                ])
+                .unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn add_interval_truncated_to_micros() {
+        let id = Id::Standard(StandardId::new(0x123).unwrap());
+        let add = Add::new(Duration::from_nanos(1500), id, &[]).unwrap();
+
+        let out = add.to_string();
+        assert_eq!(out, "< add 0 1 123 0 >");
+
+        let Command::Add(parsed) = command(&out).unwrap().1 else {
+            panic!("expected add");
+        };
+        assert_eq!(parsed.interval, Duration::from_micros(1));
+    }
+
+    #[test]
+    fn filter_interval_truncated_to_micros() {
+        let id = Id::Standard(StandardId::new(0x123).unwrap());
+        let filter =
+            Filter::new(Duration::from_nanos(2500), id, &[0xFF]).unwrap();
+
+        assert_eq!(filter.to_string(), "< filter 0 2 123 1 FF >");
+    }
+
+    #[test]
+    fn parse_add_remote_frame() {
+        let (_, result) = command("< add 1 0 123 8 >").unwrap();
+
+        assert!(match result {
+            Command::Add(add) => add.is_remote_frame(),
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn parse_update() {
+        let (_, result) = command("< update 123 3 11 22 33 >").unwrap();
+        assert_eq!(
+            result,
+            Command::Update(Update {
+                id: Id::Standard(StandardId::new(0x123).unwrap()),
+                dlc: 3,
+                data: Vec::from_slice(&[0x11, 0x22, 0x33,]).unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_max_length_data() {
+        let ff = Vec::<u8, MAX_DATA_LEN>::from_slice(&[0xFF; 8]).unwrap();
+
+        let (rest, result) =
+            command("< send 1FFFFFFF 8 FF FF FF FF FF FF FF FF >").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result,
+            Command::Send(Send {
+                id: Id::Extended(ExtendedId::MAX),
+                dlc: 8,
+                data: ff.clone(),
+            })
+        );
+
+        // all ones is beyond the 29 bit extended id range
+        assert!(command("< send FFFFFFFF 8 FF FF FF FF FF FF FF FF >").is_err());
+
+        let (rest, result) =
+            command("< add 0 100 123 8 FF FF FF FF FF FF FF FF >").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result,
+            Command::Add(Add {
+                interval: Duration::from_micros(100),
+                id: Id::Standard(StandardId::new(0x123).unwrap()),
+                dlc: 8,
+                data: ff.clone(),
+            })
+        );
+
+        let (rest, result) =
+            command("< update 123 8 FF FF FF FF FF FF FF FF >").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            result,
+            Command::Update(Update {
+                id: Id::Standard(StandardId::new(0x123).unwrap()),
+                dlc: 8,
+                data: ff,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_delete() {
+        let (_, result) = command("< delete 123 >").unwrap();
+        assert_eq!(
+            result,
+            Command::Delete(Delete {
+                id: Id::Standard(StandardId::new(0x123).unwrap())
+            })
+        );
+    }
+
+    #[test]
+    fn parse_send_no_data() {
+        let (_, result) = command("< send 123 0 >").unwrap();
+        assert_eq!(
+            result,
+            Command::Send(Send {
+                id: Id::Standard(StandardId::new(0x123).unwrap()),
+                dlc: 0,
+                data: Vec::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_send_with_data() {
+        let (_, result) = command("< send 1AAAAAAA 2 1 f1 >").unwrap();
+        assert_eq!(
+            result,
+            Command::Send(Send {
+                id: Id::Extended(ExtendedId::new(0x1AAAAAAA).unwrap()),
+                dlc: 2,
+                data: Vec::from_slice(&[0x1, 0xf1]).unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_send_lowercase() {
+        let (_, result) = command("< send 1aaaaaaa 2 ab cd >").unwrap();
+        assert_eq!(
+            result,
+            Command::Send(Send {
+                id: Id::Extended(ExtendedId::new(0x1AAAAAAA).unwrap()),
+                dlc: 2,
+                data: Vec::from_slice(&[0xAB, 0xCD]).unwrap(),
+            })
+        );
+
+        let (_, result) = command("< send 7ff 0 >").unwrap();
+        assert_eq!(
+            result,
+            Command::Send(Send {
+                id: Id::Standard(StandardId::MAX),
+                dlc: 0,
+                data: Vec::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_send_dlc_mismatch() {
+        let Err(nom::Err::Failure(err)) = command("< send 123 3 11 22 >")
+        else {
+            panic!("expected failure");
+        };
+        assert_eq!(err.kind, ErrorKind::Verify);
+    }
+
+    #[test]
+    fn parse_send_invalid_data() {
+        assert!(command("< send 123 8 01 02 03 04 05 06 07 08 09 >").is_err());
+        assert!(command("< send 123 2 01 100 02 >").is_err());
+        assert!(command("< add 1 0 123 2 01 100 02 >").is_err());
+        assert!(command("< update 123 2 01 100 02 >").is_err());
+    }
+
+    #[test]
+    fn parse_dlc_mismatch() {
+        assert!(command("< update 123 3 11 22 >").is_err());
+        assert!(command("< update 123 1 >").is_err());
+        assert!(command("< add 0 0 123 2 01 >").is_err());
+        assert!(command("< add_count 1 0 0 123 2 01 >").is_err());
+        assert!(command("< tx_setup 3 0 100000 123 1 01 02 >").is_err());
+        assert!(command("< rx_setup 20 0 0 123 2 FF >").is_err());
+
+        // remote frames carry a data length code but no data
+        assert!(command("< add 0 0 123 2 >").is_ok());
+        assert!(command("< rx_setup 20 0 0 123 2 >").is_ok());
+    }
+
+    #[test]
+    fn parse_send_remote_frame() {
+        let (_, result) = command("< send 123 2 >").unwrap();
+        assert!(match result {
+            Command::Send(send) => send.is_remote_frame(),
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn parse_send_id_length_incorrect() {
+        let result = command("< send 1234 0 >");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_send_malformed_context() {
+        let Err(nom::Err::Failure(err)) = command("< send 12 0 >") else {
+            panic!("expected failure");
+        };
+        assert_eq!(err.context, Some("malformed send command"));
+    }
+
+    #[test]
+    fn parse_filter() {
+        let id = Id::Standard(StandardId::new(0x123).unwrap());
+        assert_eq!(
+            command("< filter 0 0 123 1 FF >"),
+            Ok((
+                "",
+                Command::Filter(Filter {
+                    interval: Duration::ZERO,
+                    id,
+                    dlc: 1,
+                    data: Vec::from_slice(&[0xFF]).unwrap(),
+                    mask: Vec::new(),
+                    invert: false,
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_filter_inverted() {
+        let (_, result) = command("< filter_inv 0 0 123 1 FF >").unwrap();
+        assert_eq!(
+            result,
+            Command::Filter(Filter {
+                interval: Duration::ZERO,
+                id: Id::Standard(StandardId::new(0x123).unwrap()),
+                dlc: 1,
+                data: Vec::from_slice(&[0xFF]).unwrap(),
+                mask: Vec::new(),
+                invert: true,
+            })
+        );
+        assert_eq!(
+            peek_command_kind("< filter_inv 0 0 123 0 >"),
+            Some(CommandKind::Filter)
+        );
+    }
+
+    #[test]
+    fn parse_filter_with_mask() {
+        let (_, result) = command("< filter 0 0 123 2 11 22 FF 0F >").unwrap();
+        assert_eq!(
+            result,
+            Command::Filter(Filter {
+                interval: Duration::ZERO,
+                id: Id::Standard(StandardId::new(0x123).unwrap()),
+                dlc: 2,
+                data: Vec::from_slice(&[0x11, 0x22]).unwrap(),
+                mask: Vec::from_slice(&[0xFF, 0x0F]).unwrap(),
+                invert: false,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_filter_mask_shorter_than_dlc() {
+        let result = command("< filter 0 0 123 3 FF FF >");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_echo() {
+        let (_, result) = command("< echo >").unwrap();
+        assert_eq!(result, Command::Echo(Echo::default()));
+    }
+
+    #[test]
+    fn parse_raw_mode() {
+        let (_, result) = command("< rawmode >").unwrap();
+        assert_eq!(result, Command::RawMode(RawMode { loopback: false }));
+    }
+
+    #[test]
+    fn parse_raw_mode_loopback() {
+        let (_, result) = command("< rawmode loopback >").unwrap();
+        assert_eq!(result, Command::RawMode(RawMode { loopback: true }));
+    }
+
+    #[test]
+    fn parse_broadcast_mode() {
+        let (_, result) = command("< bcmode >").unwrap();
+        assert_eq!(
+            result,
+            Command::BroadcastMode(BroadcastMode { count: None })
+        );
+    }
+
+    #[test]
+    fn parse_broadcast_mode_count() {
+        let (_, result) = command("< bcmode 1000 >").unwrap();
+        assert_eq!(
+            result,
+            Command::BroadcastMode(BroadcastMode { count: Some(1000) })
+        );
+        assert!(command("< bcmode lots >").is_err());
+    }
+
+    #[test]
+    fn parse_control_mode() {
+        let (_, result) = command("< controlmode >").unwrap();
+        assert_eq!(result, Command::ControlMode(ControlMode { bus: None }));
+    }
+
+    #[test]
+    fn parse_control_mode_with_bus() {
+        let (_, result) = command("< controlmode can0 >").unwrap();
+        assert_eq!(
+            result,
+            Command::ControlMode(ControlMode {
+                bus: Some(String::try_from("can0").unwrap()),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_iso_tp_mode() {
+        let (_, result) = command("< isotpmode >").unwrap();
+        assert_eq!(result, Command::IsoTpMode(IsoTpMode));
+    }
+
+    #[test]
+    fn parse_auth() {
+        let (_, result) = command("< auth s3cret >").unwrap();
+        assert_eq!(
+            result,
+            Command::Auth(Auth {
+                token: String::try_from("s3cret").unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_auth_token_too_long() {
+        let input = format!("< auth {} >", "a".repeat(MAX_AUTH_TOKEN_LEN + 1));
+        assert!(command(&input).is_err());
+    }
+
+    #[test]
+    fn statistics() {
+        let (_, result) = command("< statistics 1000 >").unwrap();
+        assert_eq!(
+            result,
+            Command::Statistics(Statistics {
+                interval: Duration::from_secs(1),
+            })
+        );
+    }
+
+    #[test]
+    fn format_frame_message() {
+        let mut out = std::string::String::new();
+        format_frame(
+            &mut out,
+            Id::Standard(StandardId::new(0x123).unwrap()),
+            Duration::from_micros(1_000_500),
+            &[0x11, 0xAB],
+        )
+        .unwrap();
+        assert_eq!(out, "< frame 123 1.000500 11AB >");
+    }
+
+    #[test]
+    fn command_direction() {
+        let (_, open) = command("< open can0 >").unwrap();
+        assert_eq!(open.direction(), Direction::ClientToServer);
+        assert!(open.is_client_to_server());
+        assert!(!open.is_server_to_client());
+
+        let (_, echo) = command("< echo >").unwrap();
+        assert_eq!(echo.direction(), Direction::Bidirectional);
+        assert!(echo.is_client_to_server());
+        assert!(echo.is_server_to_client());
+    }
+
+    #[test]
+    fn update_new() {
+        let id = Id::Standard(StandardId::new(0x123).unwrap());
+        let update = Update::new(id, 2, &[0x11, 0x22]).unwrap();
+        assert_eq!(update.data, [0x11, 0x22]);
+
+        assert_eq!(Update::new(id, 3, &[0x11]), Err(UpdateError::DlcMismatch));
+        assert_eq!(Update::new(id, 9, &[0; 9]), Err(UpdateError::DataTooLong));
+    }
+
+    #[test]
+    fn update_round_trip() {
+        let id = Id::Extended(ExtendedId::new(0x1AAAAAAA).unwrap());
+        let update = Update::new(id, 2, &[0x01, 0xF1]).unwrap();
+        let out = update.to_string();
+        assert_eq!(out, "< update 1AAAAAAA 2 01 F1 >");
+        assert_eq!(command(&out).unwrap().1, Command::Update(update));
+    }
+
+    #[test]
+    fn delete_round_trip() {
+        let delete = Delete::new(Id::Standard(StandardId::new(0x7).unwrap()));
+        let out = delete.to_string();
+        assert_eq!(out, "< delete 007 >");
+        assert_eq!(command(&out).unwrap().1, Command::Delete(delete));
+    }
+
+    #[test]
+    fn parse_list_jobs() {
+        let (_, result) = command("< list_jobs >").unwrap();
+        assert_eq!(result, Command::ListJobs(ListJobs));
+    }
+
+    #[test]
+    fn job_round_trip() {
+        let job = Job {
+            interval: Duration::from_micros(1_000_250),
+            id: Id::Standard(StandardId::new(0x123).unwrap()),
+            dlc: 2,
+            data: Vec::from_slice(&[0x11, 0x22]).unwrap(),
+        };
+        let out = job.to_string();
+        assert_eq!(out, "< job 123 1 250 2 11 22 >");
+        assert_eq!(response(&out).unwrap().1, Response::Job(job));
+    }
+
+    #[test]
+    fn parse_end_list() {
+        let (_, result) = response("< end_list >").unwrap();
+        assert_eq!(result, Response::EndList(EndList));
+    }
+
+    #[test]
+    fn parse_frame() {
+        let (_, result) =
+            response("< frame 1AAAAAAA 23.000424 11AB >").unwrap();
+        let Response::Frame(frame) = result else {
+            panic!("expected frame");
+        };
+
+        assert_eq!(
+            frame.id(),
+            Id::Extended(ExtendedId::new(0x1AAAAAAA).unwrap())
+        );
+        assert_eq!(frame.timestamp, Duration::from_micros(23_000_424));
+        assert_eq!(frame.dlc(), 2);
+        assert_eq!(frame.data(), &[0x11, 0xAB]);
+        assert!(frame.is_extended());
+        assert!(frame.is_data_frame());
+    }
+
+    #[test]
+    fn parse_frame_odd_data() {
+        assert!(response("< frame 123 0.000000 11A >").is_err());
+    }
+
+    #[test]
+    fn bus_stats_round_trip() {
+        let stats = BusStats {
+            tx_ok: 10,
+            rx_ok: 20,
+            tx_err: 1,
+            rx_err: 2,
+        };
+        let out = stats.to_string();
+        assert_eq!(out, "< stat 10 20 1 2 >");
+        assert_eq!(response(&out).unwrap().1, Response::BusStats(stats));
+    }
+
+    #[test]
+    fn parse_command_line_endings() {
+        let (remainder, result) = command("< echo >\r\n").unwrap();
+        assert_eq!(remainder, "");
+        assert_eq!(result, Command::Echo(Echo::default()));
+
+        let (remainder, result) = command("< rawmode >\n< echo >").unwrap();
+        assert_eq!(remainder, "< echo >");
+        assert_eq!(result, Command::RawMode(RawMode { loopback: false }));
+
+        let (remainder, _) = command("< delete 123 >\r\n").unwrap();
+        assert_eq!(remainder, "");
+    }
+
+    #[test]
+    fn parse_command_surrounding_whitespace() {
+        assert_eq!(
+            command("  < echo >  ").unwrap(),
+            ("", Command::Echo(Echo::default()))
+        );
+        assert_eq!(
+            command("\r\n< echo >").unwrap(),
+            command("< echo >").unwrap()
+        );
+    }
+
+    #[test]
+    fn command_display_round_trip() {
+        let commands = [
+            "< open vcan1000 >",
+            "< add 1 500000 123 2 01 02 >",
+            "< update 12345678 1 FF >",
+            "< delete 123 >",
+            "< send 123 3 AA BB CC >",
+            "< send 123 2 >",
+            "< filter 0 100000 123 2 FF 00 0F F0 >",
+            "< echo >",
+            "< rawmode >",
+            "< rawmode loopback >",
+            "< bcmode >",
+            "< bcmode 1000 >",
+            "< controlmode >",
+            "< controlmode can0 >",
+            "< isotpmode >",
+            "< statistics 1000 >",
+            "< auth secret >",
+            "< list_jobs >",
+            "< get_busses >",
+            "< tx_setup 3 0 100000 123 2 01 02 >",
+            "< rx_setup 20 0 0 123 0 >",
+            "< isotpconf 7E0 7E8 normal 8 10 >",
+            "< isotpconf 18DA10F1 18DAF110 mixed 0 0 CC >",
+            "< nop >",
+            "< echo abc123 >",
+            "< echo abc123 250 >",
+            "< delete_range 100 1FF >",
+            "< add_count 5 0 100000 123 1 AA >",
+            "< setbaud 500000 >",
+            "< xlsend 00000123 7 1 3 01 02 03 >",
+            "< get_state >",
+            "< filter_inv 0 100000 123 2 FF 00 0F F0 >",
+            "< sleep >",
+            "< wakeup >",
+            "< get_version >",
+            "< open can0 raw >",
+            "< fdsend 123 3 12 00 01 02 03 04 05 06 07 08 09 0A 0B >",
+            "< subscribe_tx 1FFFFFFF >",
+            "< heartbeat 5000 >",
+            "< getbitrate >",
+            "< lasterror >",
+            "< announce node1 192.168.1.10 29536 >",
+            "< announce gw-2 fe80::1 1 >",
+            "< request_frames 123 >",
+            "< request_frames 1FFFFFFF >",
+        ];
+
+        for input in commands {
+            let (_, cmd) = command(input).unwrap();
+            let out = std::format!("{}", cmd);
+            assert_eq!(out, input);
+            assert_eq!(command(&out).unwrap().1, cmd);
+        }
+    }
+
+    #[test]
+    fn parse_get_busses() {
+        let (_, result) = command("< get_busses >").unwrap();
+        assert_eq!(result, Command::GetBusses(GetBusses));
+    }
+
+    #[test]
+    fn parse_bcm_tx_setup() {
+        let (_, result) = command("< tx_setup 13 1 0 12345678 1 FF >").unwrap();
+        let Command::BcmTxSetup(setup) = result else {
+            panic!("expected tx_setup command");
+        };
+
+        assert!(setup.flags.contains(
+            BcmFlags::SETTIMER | BcmFlags::STARTTIMER | BcmFlags::TX_CP_CAN_ID
+        ));
+        assert!(!setup.flags.contains(BcmFlags::TX_ANNOUNCE));
+        assert_eq!(setup.interval, Duration::from_secs(1));
+        assert_eq!(
+            setup.id,
+            Id::Extended(ExtendedId::new(0x12345678).unwrap())
+        );
+        assert_eq!(setup.data, [0xFF]);
+    }
+
+    #[test]
+    fn parse_bcm_rx_setup() {
+        let (_, result) =
+            command("< rx_setup 40 0 500000 123 2 FF 00 >").unwrap();
+        let Command::BcmRxSetup(setup) = result else {
+            panic!("expected rx_setup command");
+        };
+
+        assert_eq!(setup.flags, BcmFlags::RX_CHECK_DLC);
+        assert_eq!(setup.interval, Duration::from_millis(500));
+        assert_eq!(setup.id, Id::Standard(StandardId::new(0x123).unwrap()));
+        assert_eq!(setup.dlc, 2);
+        assert_eq!(setup.mask, [0xFF, 0x00]);
+    }
+
+    #[test]
+    fn peek_kind_matches_parsed_kind() {
+        let inputs = [
+            "< open can0 >",
+            "< add 1 0 123 0 >",
+            "< rawmode loopback >",
+            "< controlmode can0 >",
+            "< list_jobs >",
+            "< rx_setup 0 0 0 123 0 >",
+        ];
+
+        for input in inputs {
+            let (_, cmd) = command(input).unwrap();
+            assert_eq!(peek_command_kind(input), Some(cmd.kind()));
+        }
+
+        assert_eq!(peek_command_kind("< sendx >"), None);
+        assert_eq!(peek_command_kind("send 123 0 >"), None);
+    }
+
+    #[test]
+    fn parse_control_mode_stats() {
+        let input = "< stats 10 1 20 2 3 >";
+        let (_, result) = response(input).unwrap();
+        let stats = ControlModeStats {
+            tx_ok: 10,
+            tx_err: 1,
+            rx_ok: 20,
+            rx_err: 2,
+            bus_err: 3,
+        };
+
+        assert_eq!(result, Response::ControlModeStats(stats.clone()));
+        assert_eq!(std::format!("{}", stats), input);
+    }
+
+    #[test]
+    fn parse_iso_tp_conf() {
+        let (_, result) =
+            command("< isotpconf 7E0 7E8 extended 4 20 AA >").unwrap();
+
+        assert_eq!(
+            result,
+            Command::IsoTpConf(IsoTpConf {
+                src_id: Id::Standard(StandardId::new(0x7E0).unwrap()),
+                dst_id: Id::Standard(StandardId::new(0x7E8).unwrap()),
+                addr_mode: IsoTpAddrMode::Extended,
+                block_size: 4,
+                stmin: 20,
+                padding: Some(0xAA),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_iso_tp_conf_same_ids() {
+        let result = command("< isotpconf 7E0 7E0 normal 0 0 >");
+        assert!(matches!(result, Err(nom::Err::Failure(_))));
+    }
+
+    #[test]
+    fn parse_error_formatting() {
+        let Err(nom::Err::Failure(err)) = command("< send 1234 0 >") else {
+            panic!("expected failure");
+        };
+
+        assert_eq!(
+            std::format!("{}", err),
+            "malformed send command at \"1234 0 >\""
+        );
+
+        let long = ParseError::new(
+            "< send 123 8 00 11 22 33 44 55 66 77 >",
+            ErrorKind::Tag,
+        );
+        assert_eq!(long.snippet(), "< send 123 8 00 11 22 33 44 55 6");
+        assert!(std::format!("{:?}", long).contains("input: \"< send 123 8"));
+    }
+
+    #[test]
+    fn parse_nop() {
+        let (_, result) = command("< nop >").unwrap();
+        assert_eq!(result, Command::Nop(Nop));
+    }
+
+    #[test]
+    fn parse_add_interval_overflow() {
+        let result = command("< add 18446744073709551615 1000000 123 0 >");
+        let Err(nom::Err::Failure(err)) = result else {
+            panic!("expected failure");
+        };
+
+        assert_eq!(err.context, Some("duration out of range"));
+    }
+
+    #[test]
+    fn send_builder_errors() {
+        let id = Id::Standard(StandardId::new(0x123).unwrap());
 
-fn broadcast_mode<'a>(input: &'a str) -> IResult<&'a str, BroadcastMode> {
-    let (input, _) = tag("< bcmode >")(input)?;
+        assert_eq!(
+            Send::new(id).data(&[0; 9]).build(),
+            Err(SendError::DataTooLong)
+        );
+        assert_eq!(
+            Send::new(id).data(&[0; 2]).dlc(3).build(),
+            Err(SendError::DlcMismatch)
+        );
+        assert_eq!(
+            Send::new(id).dlc(9).rtr().build(),
+            Err(SendError::DlcTooLarge)
+        );
 
-    Ok((input, BroadcastMode))
-}
+        let send = Send::new(id).data(&[0xAA]).build().unwrap();
+        assert_eq!(std::format!("{}", send), "< send 123 1 AA >");
+    }
 
-/// Enter control mode command.
-#[derive(Debug, PartialEq, Clone)]
-#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
-pub struct ControlMode;
+    #[test]
+    fn statistics_display() {
+        let stats = Statistics {
+            interval: Duration::from_millis(500),
+        };
 
-fn control_mode<'a>(input: &'a str) -> IResult<&'a str, ControlMode> {
-    let (input, _) = tag("< controlmode >")(input)?;
+        let out = format!("{}", stats);
+        assert_eq!(out, "< statistics 500 >");
+        assert_eq!(command(&out).unwrap(), ("", Command::Statistics(stats)));
+    }
 
-    Ok((input, ControlMode))
-}
+    #[test]
+    fn statistics_interval_bounds() {
+        let ms = |millis| Duration::from_millis(millis);
 
-/// Enter ISO-TP mode command.
-#[derive(Debug, PartialEq, Clone)]
-#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
-pub struct IsoTpMode;
+        for (input, interval) in [
+            ("< statistics 0 >", ms(0)),
+            ("< statistics 10 >", MIN_STATS_INTERVAL),
+            ("< statistics 3600000 >", MAX_STATS_INTERVAL),
+        ] {
+            let (_, result) = command(input).unwrap();
+            assert_eq!(result, Command::Statistics(Statistics { interval }));
+        }
 
-fn iso_tp_mode<'a>(input: &'a str) -> IResult<&'a str, IsoTpMode> {
-    let (input, _) = tag("< isotpmode >")(input)?;
+        assert!(command("< statistics 9 >").is_err());
+        assert!(command("< statistics 3600001 >").is_err());
+    }
 
-    Ok((input, IsoTpMode))
-}
+    #[test]
+    fn parse_compound_command() {
+        let (remainder, commands) =
+            compound_command("< open vcan0; rawmode; send 123 1 AA >\r\n")
+                .unwrap();
 
-/// Statistics setting command.
-#[derive(Debug, PartialEq, Clone)]
-#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
-pub struct Statistics {
-    /// Send rate milliseconds.
-    pub interval: Duration,
-}
+        assert_eq!(remainder, "");
+        assert_eq!(commands.len(), 3);
+        assert!(matches!(commands[0], Command::Open(_)));
+        assert_eq!(commands[1], Command::RawMode(RawMode { loopback: false }));
+        assert!(matches!(commands[2], Command::Send(_)));
 
-fn statistics<'a>(input: &'a str) -> IResult<&'a str, Statistics> {
-    let (input, millis) = delimited(
-        tag("< statistics "),
-        terminated(map_res(digit1, |v: &str| u64::from_str(v)), char(' ')),
-        char('>'),
-    )(input)?;
+        let (_, commands) = compound_command("< echo >").unwrap();
+        assert_eq!(commands[..], [Command::Echo(Echo::default())]);
 
-    let interval = Duration::from_millis(millis);
+        let Err(nom::Err::Failure(err)) =
+            compound_command("< open vcan0; bogus >")
+        else {
+            panic!("expected failure");
+        };
+        assert_eq!(err.input, "bogus");
+        assert_eq!(err.context, Some("malformed compound command"));
+    }
 
-    Ok((input, Statistics { interval }))
-}
+    #[test]
+    fn bcm_job_table() {
+        let mut jobs = BcmJobTable::<2>::new();
+        let job = |input| match command(input).unwrap().1 {
+            Command::Add(add) => add,
+            _ => unreachable!(),
+        };
 
-/// Command.
-#[derive(Debug, PartialEq, Clone)]
-pub enum Command {
-    /// Open command.
-    Open(Open),
-    /// Add command.
-    Add(Add),
-    /// Update command.
-    Update(Update),
-    /// Delete command.
-    Delete(Delete),
-    /// Send command.
-    Send(Send),
-    /// Filter command.
-    Filter(Filter),
-    /// Echo command.
-    Echo(Echo),
-    /// Raw mode command.
-    RawMode(RawMode),
-    /// Broadcast mode command.
-    BroadcastMode(BroadcastMode),
-    /// Control mode command.
-    ControlMode(ControlMode),
-    /// ISO-TP mode command.
-    IsoTpMode(IsoTpMode),
-    /// Statistics command.
-    Statistics(Statistics),
-}
+        assert_eq!(jobs.insert(job("< add 1 0 123 1 AA >")), Ok(None));
+        assert_eq!(jobs.insert(job("< add 1 0 456 0 >")), Ok(None));
 
-/// Parse a socketcand command.
-pub fn command<'a>(input: &'a str) -> IResult<&'a str, Command> {
-    alt((
-        map(open, Command::Open),
-        map(add, Command::Add),
-        map(update, Command::Update),
-        map(delete, Command::Delete),
-        map(send, Command::Send),
-        map(filter, Command::Filter),
-        map(echo, Command::Echo),
-        map(raw_mode, Command::RawMode),
-        map(broadcast_mode, Command::BroadcastMode),
-        map(control_mode, Command::ControlMode),
-        map(iso_tp_mode, Command::IsoTpMode),
-        map(statistics, Command::Statistics),
-    ))(input)
-}
+        let replaced = jobs.insert(job("< add 2 0 123 1 BB >")).unwrap();
+        assert_eq!(replaced.unwrap().data, [0xAA]);
+        assert!(jobs.insert(job("< add 1 0 789 0 >")).is_err());
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use embedded_can::Frame;
+        let id = Id::Standard(StandardId::new(0x123).unwrap());
+        assert_eq!(jobs.get(id).unwrap().data, [0xBB]);
+        assert!(jobs.remove(id).is_some());
+        assert!(!jobs.contains(id));
+        assert_eq!(jobs.iter().count(), 1);
+
+        for add in &jobs {
+            assert_eq!(add.id, Id::Standard(StandardId::new(0x456).unwrap()));
+        }
+    }
 
     #[test]
-    fn parse_open() {
-        let (_, result) = command("< open vcan5 >").unwrap();
+    fn parse_echo_nonce() {
+        let (_, result) = command("< echo 42 >").unwrap();
+        let Command::Echo(echo) = result else {
+            panic!("expected echo command");
+        };
+        assert_eq!(echo.nonce.as_deref(), Some("42"));
+        assert_eq!(echo.elapsed, None);
+
+        let (_, result) = command("< echo 42 1500 >").unwrap();
+        let Command::Echo(echo) = result else {
+            panic!("expected echo command");
+        };
+        assert_eq!(echo.elapsed, Some(Duration::from_micros(1500)));
+    }
+
+    #[test]
+    fn format_frame_round_trip() {
+        let cases: [(Id, Duration, &[u8]); 4] = [
+            (
+                Id::Standard(StandardId::new(0x123).unwrap()),
+                Duration::from_micros(1_000_500),
+                &[0x11, 0xAB],
+            ),
+            (
+                Id::Extended(ExtendedId::new(0x1FFF_FFFF).unwrap()),
+                Duration::from_micros(42),
+                &[0, 1, 2, 3, 4, 5, 6, 7],
+            ),
+            (
+                Id::Standard(StandardId::new(0x7FF).unwrap()),
+                Duration::ZERO,
+                &[0xFF],
+            ),
+            (
+                Id::Standard(StandardId::new(0).unwrap()),
+                Duration::from_secs(1_700_000_000),
+                &[],
+            ),
+        ];
+
+        for (id, timestamp, data) in cases {
+            let mut out = std::string::String::new();
+            format_frame(&mut out, id, timestamp, data).unwrap();
+
+            let (remainder, result) = response(&out).unwrap();
+            assert_eq!(remainder, "");
+
+            let Response::Frame(frame) = result else {
+                panic!("expected frame response for {}", out);
+            };
+            assert_eq!(frame.id, id, "{}", out);
+            assert_eq!(frame.timestamp, timestamp, "{}", out);
+            assert_eq!(frame.data, data, "{}", out);
+        }
+    }
+
+    #[test]
+    fn parse_messages() {
+        let (_, result) = message("< hi >").unwrap();
+        assert_eq!(result, Message::Response(Response::Hi(Hi)));
+
+        let (_, result) = message("< error not_found >").unwrap();
         assert_eq!(
             result,
-            Command::Open(Open {
-                index: 5,
-                virt: true
-            })
+            Message::Response(Response::Error(Error {
+                reason: Some(String::try_from("not_found").unwrap())
+            }))
+        );
+
+        let (_, result) = message("< error >").unwrap();
+        assert_eq!(
+            result,
+            Message::Response(Response::Error(Error { reason: None }))
         );
+
+        let (_, result) = message("< frame 123 0.000001 AA >").unwrap();
+        assert!(matches!(result, Message::Response(Response::Frame(_))));
+
+        let (_, result) = message("< echo >").unwrap();
+        assert_eq!(result, Message::Command(Command::Echo(Echo::default())));
     }
 
     #[test]
-    fn parse_add() {
+    fn parse_delete_range() {
+        let (_, result) = command("< delete_range 100 1FF >").unwrap();
+        let Command::DeleteRange(range) = result else {
+            panic!("expected delete_range command");
+        };
+
+        let std_id = |raw| Id::Standard(StandardId::new(raw).unwrap());
+        assert!(range.contains(std_id(0x100)));
+        assert!(range.contains(std_id(0x1FF)));
+        assert!(!range.contains(std_id(0x200)));
+        assert!(!range.contains(Id::Extended(ExtendedId::new(0x150).unwrap())));
+
+        assert!(command("< delete_range 1FF 100 >").is_err());
+        assert!(command("< delete_range 100 000001FF >").is_err());
+    }
+
+    #[test]
+    fn parse_add_count() {
+        let (_, result) = command("< add_count 3 1 0 123 0 >").unwrap();
+        let Command::AddCount(add) = result else {
+            panic!("expected add_count command");
+        };
+        assert_eq!(add.count, 3);
+        assert_eq!(add.interval, Duration::from_secs(1));
+
+        assert!(command("< add_count 0 1 0 123 0 >").is_err());
+    }
+
+    #[test]
+    fn parse_open_hex_index() {
+        let Err(nom::Err::Failure(err)) = command("< open canFF >") else {
+            panic!("expected failure");
+        };
+        assert_eq!(err.context, Some("interface index must be decimal"));
+
+        let Err(nom::Err::Failure(err)) = command("< open slcan0 >") else {
+            panic!("expected failure");
+        };
+        assert_eq!(err.context, Some("malformed open command"));
+    }
+
+    #[test]
+    fn builder_write() {
+        let mut buf = [0; 24];
+        let mut builder = Builder::new(&mut buf);
+        assert!(builder.is_empty());
+
+        write!(builder, "< echo >").unwrap();
+        assert_eq!(builder.as_bytes(), b"< echo >");
+
+        // does not fit, nothing is written
+        assert!(builder.write_str("< statistics 1000 >").is_err());
+        assert_eq!(builder.len(), 8);
+
+        builder.clear();
+        format_frame(
+            &mut builder,
+            Id::Standard(StandardId::new(0x1).unwrap()),
+            Duration::ZERO,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(builder.as_bytes(), b"< frame 001 0.000000  >");
+    }
+
+    #[test]
+    fn parse_set_baud() {
+        let (_, result) = command("< setbaud 500000 >").unwrap();
+        assert_eq!(result, Command::SetBaud(SetBaud { rate: 500_000 }));
+
+        let Err(nom::Err::Failure(err)) = command("< setbaud 300000 >") else {
+            panic!("expected failure");
+        };
+        assert_eq!(err.context, Some("non-standard baud rate"));
+
+        let Err(nom::Err::Failure(err)) = command("< setbaud 7A120 >") else {
+            panic!("expected failure");
+        };
+        assert_eq!(err.context, Some("malformed setbaud command"));
+    }
+
+    #[test]
+    fn parse_xl_send() {
         let (_, result) =
-            command("< add 1 0 123 8 11 22 33 44 55 66 77 88 >").unwrap();
+            command("< xlsend 1ABCDEF0 7 2 3 01 02 03 >").unwrap();
         assert_eq!(
             result,
-            Command::Add(Add {
-                interval: Duration::from_secs(1),
-                id: Id::Standard(StandardId::new(0x123).unwrap()),
-                dlc: 8,
-                data: Vec::from_slice(&[
-                    0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88
-                ])
-                .unwrap(),
+            Command::XlSend(XlSend {
+                id: 0x1ABCDEF0,
+                priority: 7,
+                vc: 2,
+                dlc: 3,
+                data: xl_data(Vec::from_slice(&[1, 2, 3]).unwrap()),
             })
         );
+
+        assert!(command("< xlsend 123 0 0 0 >").is_err());
+        assert!(command("< xlsend 123 0 0 2 01 >").is_err());
     }
 
     #[test]
-    fn parse_add_remote_frame() {
-        let (_, result) = command("< add 1 0 123 8 >").unwrap();
+    fn xl_frame_round_trip() {
+        let frame = XlFrame {
+            id: 0x123,
+            priority: 1,
+            vc: 0,
+            timestamp: Duration::from_micros(1_000_500),
+            data: xl_data(Vec::from_slice(&[0xAB; 20]).unwrap()),
+        };
 
-        assert!(match result {
-            Command::Add(add) => add.is_remote_frame(),
-            _ => false,
-        });
+        let out = frame.to_string();
+        assert!(out.starts_with("< xlframe 00000123 1 0 1.000500 ABAB"));
+        assert_eq!(response(&out).unwrap().1, Response::XlFrame(frame));
     }
 
     #[test]
-    fn parse_update() {
-        let (_, result) = command("< update 123 3 11 22 33 >").unwrap();
+    fn bus_state_round_trip() {
+        for state in [
+            BusStateKind::ErrorActive,
+            BusStateKind::ErrorWarning,
+            BusStateKind::ErrorPassive,
+            BusStateKind::BusOff,
+        ] {
+            let out = BusState { state }.to_string();
+            assert_eq!(
+                response(&out).unwrap().1,
+                Response::BusState(BusState { state })
+            );
+        }
+
         assert_eq!(
-            result,
-            Command::Update(Update {
-                id: Id::Standard(StandardId::new(0x123).unwrap()),
-                dlc: 3,
-                data: Vec::from_slice(&[0x11, 0x22, 0x33,]).unwrap(),
-            })
+            command("< get_state >").unwrap().1,
+            Command::GetState(GetState)
         );
+        assert!(response("< state SLEEPING >").is_err());
     }
 
     #[test]
-    fn parse_delete() {
-        let (_, result) = command("< delete 123 >").unwrap();
+    fn frame_as_embedded_can() {
+        use embedded_can::Frame as _;
+
+        let (_, response) =
+            response("< frame 1AAAAAAA 1.000000 11AB >").unwrap();
+        let Response::Frame(frame) = response else {
+            panic!("expected frame");
+        };
+
+        assert!(frame.is_extended());
+        assert_eq!(frame.dlc(), 2);
+        assert_eq!(frame.data(), [0x11, 0xAB]);
+
+        assert!(super::Frame::new_remote(frame.id, 4).is_none());
+        assert!(super::Frame::new(frame.id, &[0; 9]).is_none());
+    }
+
+    #[test]
+    fn commands_as_embedded_can() {
+        use embedded_can::Frame as CanFrame;
+
+        let id = Id::Standard(StandardId::new(0x123).unwrap());
+
+        let add = <Add as CanFrame>::new(id, &[0x01]).unwrap();
+        assert_eq!(add.to_string(), "< add 0 0 123 1 01 >");
+        let update = <Update as CanFrame>::new(id, &[0x01]).unwrap();
+        assert_eq!(update.to_string(), "< update 123 1 01 >");
+        let send = <Send as CanFrame>::new_remote(id, 2).unwrap();
+        assert_eq!(send.to_string(), "< send 123 2 >");
+        let filter = <Filter as CanFrame>::new_remote(id, 2).unwrap();
+        assert!(filter.is_remote_frame());
+
+        assert!(<Add as CanFrame>::new(id, &[0; 9]).is_none());
+        assert!(<Update as CanFrame>::new_remote(id, 1).is_none());
+        assert!(<Send as CanFrame>::new_remote(id, 9).is_none());
+        assert!(<Filter as CanFrame>::new_remote(id, 9).is_none());
+    }
+
+    #[test]
+    fn parse_sleep() {
+        assert_eq!(command("< sleep >").unwrap(), ("", Command::Sleep(Sleep)));
+        assert_eq!(peek_command_kind("< sleep >"), Some(CommandKind::Sleep));
+    }
+
+    #[test]
+    fn parse_wakeup() {
         assert_eq!(
-            result,
-            Command::Delete(Delete {
-                id: Id::Standard(StandardId::new(0x123).unwrap())
-            })
+            command("< wakeup >").unwrap(),
+            ("", Command::Wakeup(Wakeup))
         );
+        assert_eq!(peek_command_kind("< wakeup >"), Some(CommandKind::Wakeup));
     }
 
     #[test]
-    fn parse_send_no_data() {
-        let (_, result) = command("< send 123 0 >").unwrap();
+    fn frame_error_round_trip() {
+        let error = FrameError {
+            timestamp: Duration::from_micros(1_000_500),
+            error_class: 0x04,
+            error_data: [0, 0x10, 0, 0, 0, 0, 0x7F, 0],
+        };
+
+        let out = error.to_string();
+        assert_eq!(out, "< error_frame 1.000500 04 0010000000007F00 >");
+        assert_eq!(response(&out).unwrap().1, Response::FrameError(error));
+
+        assert!(response("< error_frame 1.000000 04 0010 >").is_err());
         assert_eq!(
-            result,
-            Command::Send(Send {
-                id: Id::Standard(StandardId::new(0x123).unwrap()),
-                dlc: 0,
-                data: Vec::new(),
+            response("< error not_found >").unwrap().1,
+            Response::Error(Error {
+                reason: Some(String::try_from("not_found").unwrap())
             })
         );
     }
 
     #[test]
-    fn parse_send_with_data() {
-        let (_, result) = command("< send 1AAAAAAA 2 1 f1 >").unwrap();
+    fn version_round_trip() {
         assert_eq!(
-            result,
-            Command::Send(Send {
-                id: Id::Extended(ExtendedId::new(0x1AAAAAAA).unwrap()),
-                dlc: 2,
-                data: Vec::from_slice(&[0x1, 0xf1]).unwrap(),
-            })
+            command("< get_version >").unwrap().1,
+            Command::GetVersion(GetVersion)
         );
+
+        let version = Version {
+            version: String::try_from("1.2.0").unwrap(),
+        };
+        let out = version.to_string();
+        assert_eq!(out, "< version 1.2.0 >");
+        assert_eq!(response(&out).unwrap().1, Response::Version(version));
     }
 
     #[test]
-    fn parse_send_id_length_incorrect() {
-        let result = command("< send 1234 0 >");
-        assert!(result.is_err());
+    fn nack_round_trip() {
+        let nack = Nack {
+            reason: String::try_from("bad_id").unwrap(),
+        };
+
+        let out = nack.to_string();
+        assert_eq!(out, "< nack reason=\"bad_id\" >");
+        assert_eq!(response(&out).unwrap().1, Response::Nack(nack));
+
+        assert!(response("< nack bad_id >").is_err());
     }
 
     #[test]
-    fn parse_filter() {
-        let (_, result) = command("< filter 0 0 123 1 FF >").unwrap();
-        assert_eq!(
-            result,
-            Command::Filter(Filter {
-                interval: Duration::ZERO,
+    fn parse_fd_send_flags() {
+        for (bits, brs, esi) in [
+            (0, false, false),
+            (1, true, false),
+            (2, false, true),
+            (3, true, true),
+        ] {
+            let fd_flags = FdFlags { brs, esi };
+            assert_eq!(fd_flags.bits(), bits);
+
+            let send = FdSend {
                 id: Id::Standard(StandardId::new(0x123).unwrap()),
-                dlc: 1,
-                data: Vec::from_slice(&[0xFF]).unwrap(),
-            })
+                fd_flags,
+                len: 2,
+                data: Vec::from_slice(&[0xAB, 0xCD]).unwrap(),
+            };
+
+            let out = send.to_string();
+            assert_eq!(out, format!("< fdsend 123 {} 2 AB CD >", bits));
+            assert_eq!(command(&out).unwrap().1, Command::FdSend(send));
+        }
+
+        // not a CAN FD data length
+        assert!(
+            command("< fdsend 123 0 9 00 00 00 00 00 00 00 00 00 >").is_err()
         );
     }
 
     #[test]
-    fn parse_echo() {
-        let (_, result) = command("< echo >").unwrap();
-        assert_eq!(result, Command::Echo(Echo));
+    fn parse_subscribe_tx() {
+        let (_, result) = command("< subscribe_tx 123 >").unwrap();
+        let Command::SubscribeTx(subscribe) = result else {
+            panic!("expected subscribe_tx command");
+        };
+
+        assert_eq!(subscribe.id, Id::Standard(StandardId::new(0x123).unwrap()));
+        assert!(command("< subscribe_tx 12 >").is_err());
     }
 
     #[test]
-    fn parse_raw_mode() {
-        let (_, result) = command("< rawmode >").unwrap();
-        assert_eq!(result, Command::RawMode(RawMode));
+    fn tx_echo_round_trip() {
+        let echo = TxEcho {
+            id: Id::Standard(StandardId::new(0x123).unwrap()),
+            dlc: 2,
+            data: Vec::from_slice(&[0x01, 0x02]).unwrap(),
+        };
+
+        let out = echo.to_string();
+        assert_eq!(out, "< txecho 123 2 01 02 >");
+        assert_eq!(response(&out).unwrap().1, Response::TxEcho(echo));
+
+        assert!(response("< txecho 123 3 01 02 >").is_err());
     }
 
     #[test]
-    fn parse_broadcast_mode() {
-        let (_, result) = command("< bcmode >").unwrap();
-        assert_eq!(result, Command::BroadcastMode(BroadcastMode));
+    fn parse_heartbeat_config() {
+        let (_, result) = command("< heartbeat 2500 >").unwrap();
+        assert_eq!(
+            result,
+            Command::HeartbeatConfig(HeartbeatConfig {
+                interval: Duration::from_millis(2500),
+            })
+        );
+
+        assert!(command("< heartbeat >").is_err());
+        assert!(command("< heartbeat 1.5 >").is_err());
     }
 
     #[test]
-    fn parse_control_mode() {
-        let (_, result) = command("< controlmode >").unwrap();
-        assert_eq!(result, Command::ControlMode(ControlMode));
+    fn bitrate_round_trip() {
+        let bitrate = Bitrate { rate: 500_000 };
+
+        let out = bitrate.to_string();
+        assert_eq!(out, "< bitrate 500000 >");
+        assert_eq!(response(&out).unwrap().1, Response::Bitrate(bitrate));
+
+        assert_eq!(
+            command("< getbitrate >").unwrap().1,
+            Command::GetBitrate(GetBitrate)
+        );
+        assert!(response("< bitrate 500k >").is_err());
     }
 
     #[test]
-    fn parse_iso_tp_mode() {
-        let (_, result) = command("< isotpmode >").unwrap();
-        assert_eq!(result, Command::IsoTpMode(IsoTpMode));
+    fn last_error_round_trip() {
+        let code = LastErrorCode { code: 42 };
+
+        let out = code.to_string();
+        assert_eq!(out, "< last_error 42 >");
+        assert_eq!(response(&out).unwrap().1, Response::LastErrorCode(code));
+
+        assert_eq!(
+            command("< lasterror >").unwrap().1,
+            Command::LastError(LastError)
+        );
+        assert!(response("< last_error >").is_err());
     }
 
     #[test]
-    fn statistics() {
-        let (_, result) = command("< statistics 1000 >").unwrap();
+    fn parse_announce() {
         assert_eq!(
-            result,
-            Command::Statistics(Statistics {
-                interval: Duration::from_secs(1),
+            command("< announce node1 10.0.0.1 29536 >").unwrap().1,
+            Command::Announce(Announce {
+                name: String::try_from("node1").unwrap(),
+                ip: IpAddr::from([10, 0, 0, 1]),
+                port: 29536,
             })
         );
+        assert!(command("< announce node1 10.0.0 29536 >").is_err());
+        assert!(command("< announce node1 10.0.0.1 65536 >").is_err());
+        assert!(command(&format!(
+            "< announce {} 10.0.0.1 1 >",
+            "a".repeat(MAX_ANNOUNCE_NAME_LEN + 1)
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn parse_request_frames() {
+        let (_, result) = command("< request_frames 1FFFFFFF >").unwrap();
+        let Command::RequestFrames(request) = result else {
+            panic!("expected request_frames command");
+        };
+
+        assert_eq!(
+            request.id,
+            Id::Extended(ExtendedId::new(0x1FFFFFFF).unwrap())
+        );
+        assert!(command("< request_frames >").is_err());
+    }
+
+    #[test]
+    fn data_len_limits() {
+        let id = StandardId::new(0x123).unwrap();
+
+        // classic CAN frames carry at most eight bytes
+        assert_eq!(MAX_DATA_LEN, 8);
+        assert!(<super::Frame as Frame>::new(id, &[0; MAX_DATA_LEN]).is_some());
+        assert!(
+            <super::Frame as Frame>::new(id, &[0; MAX_DATA_LEN + 1]).is_none()
+        );
+
+        // CAN FD frames carry at most 64 bytes
+        assert_eq!(MAX_FD_DATA_LEN, 64);
+        let data = "00 ".repeat(MAX_FD_DATA_LEN);
+        assert!(command(&format!("< fdsend 123 0 64 {}>", data)).is_ok());
+        assert!(command(&format!("< fdsend 123 0 65 {}00 >", data)).is_err());
     }
 }
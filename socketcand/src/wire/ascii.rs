@@ -0,0 +1,23 @@
+//! ASCII input helpers.
+
+/// Returns `true` if the byte may appear in a socketcand command.
+///
+/// This is printable ASCII plus `\t`, `\r` and `\n`.
+pub fn is_valid_command_byte(b: u8) -> bool {
+    matches!(b, b' '..=b'~' | b'\t' | b'\r' | b'\n')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_command_bytes() {
+        assert!(b"< send 123 0 >\r\n\t"
+            .iter()
+            .all(|&b| is_valid_command_byte(b)));
+        assert!(!is_valid_command_byte(0x00));
+        assert!(!is_valid_command_byte(0x7F));
+        assert!(!is_valid_command_byte(0xC3));
+    }
+}
@@ -0,0 +1,86 @@
+//! Framing for [`bytes`] buffers.
+
+use super::{command, Command};
+use bytes::BytesMut;
+use core::{fmt::Write, str::from_utf8};
+
+/// Command codec for [`BytesMut`] buffers.
+///
+/// Commands are delimited by `< ... >`; anything between commands, such as
+/// line endings, is ignored.
+///
+/// # Example
+/// ```rust
+/// use bytes::BytesMut;
+/// use socketcand::wire::{Codec, Command, Echo};
+///
+/// let mut buf = BytesMut::new();
+/// Codec::encode(&Command::Echo(Echo::default()), &mut buf);
+/// assert_eq!(&buf[..], b"< echo >");
+///
+/// assert_eq!(Codec::decode(&mut buf), Some(Command::Echo(Echo::default())));
+/// assert!(buf.is_empty());
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Codec;
+
+impl Codec {
+    /// Decode the next command from `src`, consuming its bytes.
+    ///
+    /// Returns `None` if `src` does not yet contain a complete command.
+    /// Malformed commands are discarded.
+    pub fn decode(src: &mut BytesMut) -> Option<Command> {
+        loop {
+            let end = src.iter().position(|&b| b == b'>')?;
+            let frame = src.split_to(end + 1);
+
+            let Ok(frame) = from_utf8(&frame) else {
+                continue;
+            };
+
+            if let Ok((_, cmd)) = command(frame.trim_start()) {
+                return Some(cmd);
+            }
+        }
+    }
+
+    /// Encode a command into `dst`.
+    pub fn encode(cmd: &Command, dst: &mut BytesMut) {
+        // writing to `BytesMut` grows the buffer and cannot fail
+        write!(dst, "{}", cmd).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::{Echo, RawMode};
+
+    #[test]
+    fn decode_partial() {
+        let mut buf = BytesMut::from("< echo >\r\n< rawmode");
+
+        assert_eq!(
+            Codec::decode(&mut buf),
+            Some(Command::Echo(Echo::default()))
+        );
+        assert_eq!(Codec::decode(&mut buf), None);
+
+        buf.extend_from_slice(b" >");
+        assert_eq!(
+            Codec::decode(&mut buf),
+            Some(Command::RawMode(RawMode { loopback: false }))
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn decode_skips_malformed() {
+        let mut buf = BytesMut::from("< bogus >< echo >");
+
+        assert_eq!(
+            Codec::decode(&mut buf),
+            Some(Command::Echo(Echo::default()))
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! Hand-written parsers for the most frequent messages.
+//!
+//! [`command`] and [`response`] are drop-in alternatives to
+//! [`wire::command`](super::command) and [`wire::response`](super::response).
+//! `< send >` commands and `< frame >` responses are parsed by a single pass
+//! over the input bytes, without any combinators. Everything else, including
+//! malformed `< send >` and `< frame >` messages, is handed to the `nom`
+//! parsers, so results and errors are the same as theirs.
+//!
+//! # Example
+//! ```rust
+//! use socketcand::wire::{fast, Command};
+//!
+//! let (_, cmd) = fast::command("< send 123 2 01 02 >").unwrap();
+//! assert!(matches!(cmd, Command::Send(_)));
+//! ```
+
+use super::{Command, Frame, IResult, Response, Send, MAX_DATA_LEN};
+use core::time::Duration;
+use embedded_can::{ExtendedId, Id, StandardId};
+use heapless::Vec;
+
+/// Parse a socketcand command.
+///
+/// Equivalent to [`wire::command`](super::command).
+#[inline]
+pub fn command(input: &str) -> IResult<'_, Command> {
+    match send(input) {
+        Some((rest, send)) => Ok((rest, Command::Send(send))),
+        None => super::command(input),
+    }
+}
+
+/// Parse a socketcand response.
+///
+/// Equivalent to [`wire::response`](super::response).
+#[inline]
+pub fn response(input: &str) -> IResult<'_, Response> {
+    match frame(input) {
+        Some((rest, frame)) => Ok((rest, Response::Frame(frame))),
+        None => super::response(input),
+    }
+}
+
+/// Cursor over the input bytes.
+struct Cursor<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn rest(&self) -> &'a str {
+        // only ever advanced past ASCII bytes
+        self.input.get(self.pos..).unwrap_or_default()
+    }
+
+    /// Consume `tag` if the input continues with it.
+    fn tag(&mut self, tag: &str) -> Option<()> {
+        self.rest().starts_with(tag).then(|| self.pos += tag.len())
+    }
+
+    /// Consume bytes while `f` holds, returning how many were consumed.
+    fn skip_while(&mut self, f: impl Fn(u8) -> bool) -> usize {
+        let start = self.pos;
+
+        while self.peek().is_some_and(&f) {
+            self.pos += 1;
+        }
+
+        self.pos - start
+    }
+
+    /// Consume a run of decimal digits.
+    fn decimal(&mut self) -> Option<u64> {
+        let mut value: u64 = 0;
+        let start = self.pos;
+
+        while let Some(digit @ b'0'..=b'9') = self.peek() {
+            value = value
+                .checked_mul(10)?
+                .checked_add(u64::from(digit - b'0'))?;
+            self.pos += 1;
+        }
+
+        (self.pos > start).then_some(value)
+    }
+
+    /// Consume a CAN identifier of 3 or 8 hex digits followed by a space.
+    fn id(&mut self) -> Option<Id> {
+        let start = self.pos;
+        let len = self.skip_while(|b| b.is_ascii_hexdigit());
+        let raw = u32::from_str_radix(self.input.get(start..self.pos)?, 16);
+        self.tag(" ")?;
+
+        match (len, raw.ok()?) {
+            (3, raw) => StandardId::new(raw as u16).map(Id::Standard),
+            (8, raw) => ExtendedId::new(raw).map(Id::Extended),
+            _ => None,
+        }
+    }
+}
+
+/// Value of a hex digit.
+fn hex_value(b: u8) -> u8 {
+    match b {
+        b'0'..=b'9' => b - b'0',
+        b'a'..=b'f' => b - b'a' + 10,
+        _ => b - b'A' + 10,
+    }
+}
+
+/// Skip leading or trailing whitespace as `multispace0` does.
+fn is_multispace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+/// Parse `< send ID DLC DATA >`, surrounded by optional whitespace.
+#[inline]
+fn send(input: &str) -> Option<(&str, Send)> {
+    let mut cursor = Cursor::new(input);
+
+    cursor.skip_while(is_multispace);
+    cursor.tag("< send ")?;
+
+    let id = cursor.id()?;
+    let dlc = u8::try_from(cursor.decimal()?).ok()?;
+    cursor.tag(" ")?;
+
+    // space separated hex bytes, rejecting tokens that are not a valid byte
+    // and any beyond the eighth, as `util::parse_hex_bytes` does
+    let mut data = Vec::<u8, MAX_DATA_LEN>::new();
+    let mut token: Option<u16> = None;
+
+    loop {
+        match cursor.peek()? {
+            b if b.is_ascii_hexdigit() => {
+                let value = token.unwrap_or(0) * 16 + u16::from(hex_value(b));
+                // saturate above a byte, the token is invalid either way
+                token = Some(value.min(0x100));
+            }
+            b' ' | b'>' => {
+                if let Some(value) = token.take() {
+                    data.push(u8::try_from(value).ok()?).ok()?;
+                }
+
+                if cursor.peek() == Some(b'>') {
+                    break;
+                }
+            }
+            _ => return None,
+        }
+
+        cursor.pos += 1;
+    }
+
+    // remote frames carry a data length code but no data
+    if !data.is_empty() && dlc as usize != data.len() {
+        return None;
+    }
+
+    cursor.tag(">")?;
+    cursor.skip_while(is_multispace);
+
+    Some((cursor.rest(), Send { id, dlc, data }))
+}
+
+/// Parse `< frame ID SECS.USECS DATA >`.
+#[inline]
+fn frame(input: &str) -> Option<(&str, Frame)> {
+    let mut cursor = Cursor::new(input);
+
+    cursor.tag("< frame ")?;
+
+    let id = cursor.id()?;
+    let secs = cursor.decimal()?;
+    cursor.tag(".")?;
+    let micros = cursor.decimal()?;
+    cursor.tag(" ")?;
+
+    // unseparated pairs of hex digits
+    let start = cursor.pos;
+    let len = cursor.skip_while(|b| b.is_ascii_hexdigit());
+
+    if !len.is_multiple_of(2) || len / 2 > MAX_DATA_LEN {
+        return None;
+    }
+
+    let mut data = Vec::<u8, MAX_DATA_LEN>::new();
+
+    for pair in input.as_bytes().get(start..cursor.pos)?.chunks_exact(2) {
+        let &[high, low] = pair else {
+            return None;
+        };
+        data.push((hex_value(high) << 4) | hex_value(low)).ok()?;
+    }
+
+    cursor.tag(" >")?;
+
+    let timestamp =
+        Duration::from_secs(secs).checked_add(Duration::from_micros(micros))?;
+
+    Some((
+        cursor.rest(),
+        Frame {
+            id,
+            timestamp,
+            data,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn takes_fast_path() {
+        assert!(send("< send 123 2 01 02 >").is_some());
+        assert!(frame("< frame 123 1.000500 0102 >").is_some());
+    }
+
+    #[test]
+    fn matches_nom_on_examples() {
+        let commands = [
+            "< send 123 2 01 02 >",
+            " \r\n< send 12345678 0 >\n",
+            "< send 123 4 >",
+            "< send 123 1 0FF >",
+            "< send 123 2 01 100 02 >",
+            "< send 123 3 01 02 >",
+            "< send 1234 1 01 >",
+            "< send 800 1 01 >",
+            "< send 123 256 >",
+            "< send 123 8 01 02 03 04 05 06 07 08 09 >",
+            "< send 123 1 01",
+            "< open can0 >",
+        ];
+
+        for input in commands {
+            assert_eq!(
+                command(input).ok(),
+                super::super::command(input).ok(),
+                "{:?}",
+                input
+            );
+        }
+
+        let responses = [
+            "< frame 123 1.000500 0102 >",
+            "< frame 1FFFFFFF 0.0  >",
+            "< frame 123 1.5 012 >",
+            "< frame 123 1.5 010203040506070809 >",
+            "< frame 123 18446744073709551615.1000000 >",
+            "< frame 123 1.5 01",
+            "< hi >",
+        ];
+
+        for input in responses {
+            assert_eq!(
+                response(input).ok(),
+                super::super::response(input).ok(),
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn send_matches_nom(
+            input in "[ \r\n]{0,2}< send [0-9A-Fa-f]{1,9} [0-9]{1,3} [0-9A-Fa-f ]{0,30}>?[ \n]{0,2}",
+        ) {
+            prop_assert_eq!(
+                command(&input).ok(),
+                super::super::command(&input).ok()
+            );
+        }
+
+        #[test]
+        fn frame_matches_nom(
+            input in "< frame [0-9A-Fa-f]{1,9} [0-9]{1,21}\\.[0-9]{1,21} [0-9A-Fa-f]{0,18}( >)?",
+        ) {
+            prop_assert_eq!(
+                response(&input).ok(),
+                super::super::response(&input).ok()
+            );
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Fuzzing entry points.
+//!
+//! # Example
+//! A `cargo-fuzz` target only needs to forward its input:
+//! ```rust,ignore
+//! #![no_main]
+//!
+//! libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+//!     socketcand::wire::fuzz::fuzz_command(data);
+//! });
+//! ```
+
+use super::{command, MAX_XL_DATA_LEN};
+use core::{fmt::Write, str::from_utf8};
+use heapless::String;
+
+/// Longest formatted command: an `< xlsend >` with a full payload, at three
+/// characters per data byte, plus its header fields.
+const MAX_FORMATTED_LEN: usize = 3 * MAX_XL_DATA_LEN + 64;
+
+/// Run the command parser over arbitrary input.
+///
+/// Valid commands are formatted back to text, which must parse to the same
+/// command.
+///
+/// # Panics
+///
+/// Panics if a parsed command does not survive the round trip.
+// panicking is how a failure is reported to the fuzzer
+#[allow(clippy::panic, clippy::expect_used)]
+pub fn fuzz_command(data: &[u8]) {
+    let Ok(input) = from_utf8(data) else {
+        return;
+    };
+
+    let Ok((_, cmd)) = command(input) else {
+        return;
+    };
+
+    let mut out = String::<MAX_FORMATTED_LEN>::new();
+    write!(&mut out, "{}", cmd).expect("formatted command too long");
+
+    match command(&out) {
+        Ok((_, reparsed)) => assert_eq!(reparsed, cmd, "round trip of {}", out),
+        Err(err) => {
+            panic!("formatted command {} does not parse: {:?}", out, err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzz_valid_and_invalid_input() {
+        fuzz_command(b"< send 123 2 01 02 >");
+        fuzz_command(b"< filter 0 0 123 1 FF 0F >");
+        fuzz_command(b"< open vcan1 ");
+        fuzz_command(&[0xFF, 0xFE]);
+    }
+
+    #[test]
+    fn fuzz_full_xl_payload() {
+        let mut input =
+            std::format!("< xlsend 1ABCDEF0 255 255 {} ", MAX_XL_DATA_LEN);
+        input.push_str(&"FF ".repeat(MAX_XL_DATA_LEN));
+        input.push('>');
+
+        assert!(command(&input).is_ok());
+        fuzz_command(input.as_bytes());
+    }
+
+    #[test]
+    fn fuzz_corpus() {
+        let dir = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../fuzz/corpus/from_wireshark"
+        );
+
+        // the corpus is outside the crate, so missing from packaged sources
+        if !std::path::Path::new(dir).is_dir() {
+            return;
+        }
+
+        let mut commands = 0;
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let seed = std::fs::read(entry.unwrap().path()).unwrap();
+            fuzz_command(&seed);
+
+            let parsed = from_utf8(&seed).ok().and_then(|s| command(s).ok());
+            if matches!(parsed, Some(("", _))) {
+                commands += 1;
+            }
+        }
+
+        assert!(commands >= 20);
+    }
+}
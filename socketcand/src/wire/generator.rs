@@ -0,0 +1,504 @@
+//! [`Arbitrary`] implementations for commands.
+//!
+//! Generated commands are always valid: formatting one and parsing the
+//! result with [`command`](super::command) gives back the same command. This
+//! makes them suitable for property tests and structure aware fuzzing.
+//!
+//! [`Id`] and [`Duration`] are foreign types, so they are generated by the
+//! [`id`] and [`interval`] functions rather than through [`Arbitrary`].
+//!
+//! # Example
+//! ```rust
+//! use arbitrary::{Arbitrary, Unstructured};
+//! use socketcand::wire::{command, Command};
+//!
+//! let mut u = Unstructured::new(&[7, 1, 2, 3, 4, 5, 6, 7, 8]);
+//! let cmd = Command::arbitrary(&mut u).unwrap();
+//!
+//! let text = format!("{}", cmd);
+//! assert_eq!(command(&text).unwrap().1, cmd);
+//! ```
+
+use super::{
+    xl_data, Add, AddCount, Announce, Auth, BcmFlags, BcmRxSetup, BcmTxSetup,
+    BroadcastMode, Command, ControlMode, Delete, DeleteRange, Echo, FdFlags,
+    FdSend, Filter, GetBitrate, GetBusses, GetState, GetVersion,
+    HeartbeatConfig, IsoTpAddrMode, IsoTpConf, IsoTpMode, LastError, ListJobs,
+    Nop, Open, RawMode, RequestFrames, Send, SetBaud, Sleep, Statistics,
+    SubscribeTx, Update, Wakeup, XlSend, MAX_DATA_LEN, MAX_FD_DATA_LEN,
+    MAX_STATS_INTERVAL, MAX_XL_DATA_LEN, MIN_STATS_INTERVAL,
+    STANDARD_BAUD_RATES,
+};
+use crate::bus::BusType;
+use crate::Mode;
+use arbitrary::{Arbitrary, Result, Unstructured};
+use core::net::IpAddr;
+use core::time::Duration;
+use embedded_can::{ExtendedId, Id, StandardId};
+use heapless::{String, Vec};
+
+/// Characters used for generated tokens such as echo nonces.
+const TOKEN_CHARS: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz_-.";
+
+/// Generate a standard or extended CAN identifier.
+pub fn id(u: &mut Unstructured<'_>) -> Result<Id> {
+    Ok(if u.arbitrary()? {
+        Id::Extended(
+            ExtendedId::new(u.int_in_range(0..=ExtendedId::MAX.as_raw())?)
+                .unwrap_or(ExtendedId::ZERO),
+        )
+    } else {
+        Id::Standard(
+            StandardId::new(u.int_in_range(0..=StandardId::MAX.as_raw())?)
+                .unwrap_or(StandardId::ZERO),
+        )
+    })
+}
+
+/// Generate an interval with microsecond precision, as sent on the wire.
+pub fn interval(u: &mut Unstructured<'_>) -> Result<Duration> {
+    Ok(Duration::from_secs(u.arbitrary()?)
+        + Duration::from_micros(u.int_in_range(0..=999_999)?))
+}
+
+/// Generate up to `N` data bytes.
+fn data<const N: usize>(u: &mut Unstructured<'_>) -> Result<Vec<u8, N>> {
+    let len = u.int_in_range(0..=N)?;
+    bytes(u, len)
+}
+
+/// Generate exactly `len` data bytes.
+fn bytes<const N: usize>(
+    u: &mut Unstructured<'_>,
+    len: usize,
+) -> Result<Vec<u8, N>> {
+    let mut out = Vec::new();
+
+    for _ in 0..len.min(N) {
+        // capacity is checked by the loop bound
+        out.push(u.arbitrary()?).ok();
+    }
+
+    Ok(out)
+}
+
+/// Generate a token of 1 to `N` characters without spaces or `>`.
+fn token<const N: usize>(u: &mut Unstructured<'_>) -> Result<String<N>> {
+    let len = u.int_in_range(1..=N)?;
+    let mut out = String::new();
+
+    for _ in 0..len {
+        // capacity is checked by the loop bound
+        out.push(*u.choose(TOKEN_CHARS)? as char).ok();
+    }
+
+    Ok(out)
+}
+
+impl<'a> Arbitrary<'a> for Open {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            index: u.arbitrary()?,
+            bus_type: *u.choose(&[BusType::Physical, BusType::Virtual])?,
+            mode: if u.arbitrary()? {
+                Some(
+                    u.choose(&[
+                        Mode::Broadcast,
+                        Mode::Raw,
+                        Mode::Control,
+                        Mode::IsoTp,
+                    ])?
+                    .clone(),
+                )
+            } else {
+                None
+            },
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Add {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let interval = interval(u)?;
+        let id = id(u)?;
+        let data = data(u)?;
+
+        Ok(Self {
+            interval,
+            id,
+            dlc: data.len() as u8,
+            data,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Update {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let id = id(u)?;
+        let data = data(u)?;
+
+        Ok(Self {
+            id,
+            dlc: data.len() as u8,
+            data,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Delete {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self { id: id(u)? })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Send {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let id = id(u)?;
+        let dlc = u.int_in_range(0..=MAX_DATA_LEN as u8)?;
+        // remote frames carry a data length code but no data
+        let remote = u.arbitrary()?;
+        let data = bytes(u, if remote { 0 } else { dlc as usize })?;
+
+        Ok(Self { id, dlc, data })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Filter {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let interval = interval(u)?;
+        let id = id(u)?;
+        let dlc = u.int_in_range(0..=MAX_DATA_LEN as u8)?;
+        let data = bytes(u, dlc as usize)?;
+        let masked = u.arbitrary()?;
+        let mask = bytes(u, if masked { dlc as usize } else { 0 })?;
+
+        Ok(Self {
+            interval,
+            id,
+            dlc,
+            data,
+            mask,
+            invert: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Echo {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        // elapsed time is only sent along with a nonce
+        if !u.arbitrary()? {
+            return Ok(Self::default());
+        }
+
+        Ok(Self {
+            nonce: Some(token(u)?),
+            elapsed: if u.arbitrary()? {
+                Some(Duration::from_micros(u.arbitrary()?))
+            } else {
+                None
+            },
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for RawMode {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            loopback: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for BroadcastMode {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            count: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for ControlMode {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            bus: if u.arbitrary()? {
+                Some(token(u)?)
+            } else {
+                None
+            },
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Statistics {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        // zero disables statistics
+        let millis = if u.arbitrary()? {
+            0
+        } else {
+            u.int_in_range(
+                MIN_STATS_INTERVAL.as_millis() as u64
+                    ..=MAX_STATS_INTERVAL.as_millis() as u64,
+            )?
+        };
+
+        Ok(Self {
+            interval: Duration::from_millis(millis),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Auth {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self { token: token(u)? })
+    }
+}
+
+impl<'a> Arbitrary<'a> for BcmTxSetup {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let flags = BcmFlags(u.arbitrary()?);
+        let interval = interval(u)?;
+        let id = id(u)?;
+        let data = data(u)?;
+
+        Ok(Self {
+            flags,
+            interval,
+            id,
+            dlc: data.len() as u8,
+            data,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for BcmRxSetup {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let flags = BcmFlags(u.arbitrary()?);
+        let interval = interval(u)?;
+        let id = id(u)?;
+        let mask = data(u)?;
+
+        Ok(Self {
+            flags,
+            interval,
+            id,
+            dlc: mask.len() as u8,
+            mask,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for IsoTpConf {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let src_id = id(u)?;
+        let mut dst_id = id(u)?;
+
+        // a channel cannot talk to itself
+        if dst_id == src_id {
+            dst_id = match src_id {
+                Id::Standard(_) => Id::Extended(ExtendedId::MAX),
+                Id::Extended(_) => Id::Standard(StandardId::MAX),
+            };
+        }
+
+        Ok(Self {
+            src_id,
+            dst_id,
+            addr_mode: *u.choose(&[
+                IsoTpAddrMode::Normal,
+                IsoTpAddrMode::Extended,
+                IsoTpAddrMode::Mixed,
+            ])?,
+            block_size: u.arbitrary()?,
+            stmin: u.arbitrary()?,
+            padding: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for DeleteRange {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let (start, end) = match id(u)? {
+            Id::Standard(start) => {
+                let end =
+                    u.int_in_range(start.as_raw()..=StandardId::MAX.as_raw())?;
+                let end = StandardId::new(end).unwrap_or(StandardId::MAX);
+                (Id::Standard(start), Id::Standard(end))
+            }
+            Id::Extended(start) => {
+                let end =
+                    u.int_in_range(start.as_raw()..=ExtendedId::MAX.as_raw())?;
+                let end = ExtendedId::new(end).unwrap_or(ExtendedId::MAX);
+                (Id::Extended(start), Id::Extended(end))
+            }
+        };
+
+        Ok(Self { start, end })
+    }
+}
+
+impl<'a> Arbitrary<'a> for AddCount {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let count = u.int_in_range(1..=u32::MAX)?;
+        let Add {
+            interval,
+            id,
+            dlc,
+            data,
+        } = u.arbitrary()?;
+
+        Ok(Self {
+            count,
+            interval,
+            id,
+            dlc,
+            data,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for SetBaud {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            rate: *u.choose(&STANDARD_BAUD_RATES)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for XlSend {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let id = u.arbitrary()?;
+        let priority = u.arbitrary()?;
+        let vc = u.arbitrary()?;
+        let len = u.int_in_range(1..=MAX_XL_DATA_LEN)?;
+        let data = bytes(u, len)?;
+
+        Ok(Self {
+            id,
+            priority,
+            vc,
+            dlc: len as u16,
+            data: xl_data(data),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for FdSend {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let id = id(u)?;
+        let fd_flags = FdFlags {
+            brs: u.arbitrary()?,
+            esi: u.arbitrary()?,
+        };
+        let len = *u
+            .choose(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 12, 16, 20, 24, 32, 48, 64])?;
+        let data = bytes::<MAX_FD_DATA_LEN>(u, len as usize)?;
+
+        Ok(Self {
+            id,
+            fd_flags,
+            len,
+            data,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for SubscribeTx {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self { id: id(u)? })
+    }
+}
+
+impl<'a> Arbitrary<'a> for HeartbeatConfig {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            interval: Duration::from_millis(u.arbitrary()?),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Announce {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let ip = if u.arbitrary()? {
+            IpAddr::from(u.arbitrary::<[u8; 4]>()?)
+        } else {
+            IpAddr::from(u.arbitrary::<[u16; 8]>()?)
+        };
+
+        Ok(Self {
+            name: token(u)?,
+            ip,
+            port: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for RequestFrames {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self { id: id(u)? })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Command {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=33)? {
+            0 => Command::Open(u.arbitrary()?),
+            1 => Command::Add(u.arbitrary()?),
+            2 => Command::Update(u.arbitrary()?),
+            3 => Command::Delete(u.arbitrary()?),
+            4 => Command::Send(u.arbitrary()?),
+            5 => Command::Filter(u.arbitrary()?),
+            6 => Command::Echo(u.arbitrary()?),
+            7 => Command::RawMode(u.arbitrary()?),
+            8 => Command::BroadcastMode(u.arbitrary()?),
+            9 => Command::ControlMode(u.arbitrary()?),
+            10 => Command::IsoTpMode(IsoTpMode),
+            11 => Command::Statistics(u.arbitrary()?),
+            12 => Command::Auth(u.arbitrary()?),
+            13 => Command::ListJobs(ListJobs),
+            14 => Command::GetBusses(GetBusses),
+            15 => Command::BcmTxSetup(u.arbitrary()?),
+            16 => Command::BcmRxSetup(u.arbitrary()?),
+            17 => Command::IsoTpConf(u.arbitrary()?),
+            18 => Command::Nop(Nop),
+            19 => Command::DeleteRange(u.arbitrary()?),
+            20 => Command::AddCount(u.arbitrary()?),
+            21 => Command::SetBaud(u.arbitrary()?),
+            22 => Command::XlSend(u.arbitrary()?),
+            23 => Command::GetState(GetState),
+            24 => Command::Sleep(Sleep),
+            25 => Command::Wakeup(Wakeup),
+            26 => Command::GetVersion(GetVersion),
+            27 => Command::FdSend(u.arbitrary()?),
+            28 => Command::SubscribeTx(u.arbitrary()?),
+            29 => Command::HeartbeatConfig(u.arbitrary()?),
+            30 => Command::GetBitrate(GetBitrate),
+            31 => Command::LastError(LastError),
+            32 => Command::Announce(u.arbitrary()?),
+            _ => Command::RequestFrames(u.arbitrary()?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::command;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn generated_commands_round_trip(
+            data in prop::collection::vec(any::<u8>(), 0..512),
+        ) {
+            let mut u = Unstructured::new(&data);
+            let cmd = Command::arbitrary(&mut u).unwrap();
+
+            let text = std::format!("{}", cmd);
+            let (rest, parsed) = command(&text).unwrap();
+
+            prop_assert_eq!(rest, "");
+            prop_assert_eq!(parsed, cmd);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Parsing utilities.
+
+use heapless::{String, Vec};
+
+/// Error returned by [`parse_hex_bytes`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum HexBytesError {
+    /// A token is not a valid hex byte.
+    InvalidByte,
+    /// The input holds more bytes than fit the output.
+    TooLong,
+}
+
+/// Parse whitespace separated hex bytes (e.g. `"DE AD BE EF"`).
+///
+/// Fails if a token is not a valid hex byte or there are more than `N`
+/// bytes.
+pub fn parse_hex_bytes<const N: usize>(
+    input: &str,
+) -> Result<Vec<u8, N>, HexBytesError> {
+    let mut out = Vec::new();
+
+    for byte in input.split_whitespace() {
+        let byte = u8::from_str_radix(byte, 16)
+            .map_err(|_| HexBytesError::InvalidByte)?;
+        out.push(byte).map_err(|_| HexBytesError::TooLong)?;
+    }
+
+    Ok(out)
+}
+
+/// Error returned by [`normalize_whitespace`] when the normalized input does
+/// not fit the output.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct InputTooLong;
+
+/// Collapse runs of spaces and tabs in `input` to a single space.
+///
+/// Lets commands from tools that pad their fields, such as
+/// `< send  123   2  AB CD >`, be passed to [`command`](super::command).
+/// The result is written to `output` and returned.
+///
+/// # Example
+/// ```rust
+/// use heapless::String;
+/// use socketcand::wire::{command, util::normalize_whitespace};
+///
+/// let mut buf = String::new();
+/// let input = normalize_whitespace("< send  123\t2  AB CD >", &mut buf);
+/// assert!(command(input.unwrap()).is_ok());
+/// ```
+pub fn normalize_whitespace<'a>(
+    input: &str,
+    output: &'a mut String<256>,
+) -> Result<&'a str, InputTooLong> {
+    output.clear();
+
+    let mut blank = false;
+
+    for c in input.chars() {
+        let c = match c {
+            ' ' | '\t' if blank => continue,
+            ' ' | '\t' => ' ',
+            c => c,
+        };
+
+        blank = c == ' ';
+        output.push(c).map_err(|_| InputTooLong)?;
+    }
+
+    Ok(output.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_bytes() {
+        assert_eq!(
+            parse_hex_bytes::<8>("DE AD be ef").unwrap(),
+            [0xDE, 0xAD, 0xBE, 0xEF]
+        );
+        assert_eq!(parse_hex_bytes::<8>("  ").unwrap(), []);
+        assert_eq!(
+            parse_hex_bytes::<2>("01 02 03"),
+            Err(HexBytesError::TooLong)
+        );
+        assert_eq!(
+            parse_hex_bytes::<8>("01 100"),
+            Err(HexBytesError::InvalidByte)
+        );
+    }
+
+    #[test]
+    fn collapse_whitespace() {
+        let mut out = String::new();
+
+        assert_eq!(
+            normalize_whitespace("< send  123 \t 2  AB   CD >", &mut out),
+            Ok("< send 123 2 AB CD >")
+        );
+        assert_eq!(normalize_whitespace("< echo >", &mut out), Ok("< echo >"));
+        assert_eq!(
+            normalize_whitespace(&"x".repeat(257), &mut out),
+            Err(InputTooLong)
+        );
+    }
+}
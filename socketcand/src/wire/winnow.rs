@@ -0,0 +1,839 @@
+//! Command parser built on [`winnow`].
+//!
+//! An alternative to the `nom` based [`command`](super::command) parser. It
+//! accepts the same input and produces the same commands, but reports errors
+//! as a [`winnow::error::ContextError`].
+//!
+//! # Example
+//! ```rust
+//! use socketcand::wire::{winnow::command, Command};
+//!
+//! let mut input = "< send 123 2 01 02 >";
+//! let cmd = command(&mut input).unwrap();
+//!
+//! assert!(matches!(cmd, Command::Send(_)));
+//! assert!(input.is_empty());
+//! ```
+
+use super::{
+    raw_id, util::parse_hex_bytes, xl_data, Add, AddCount, Announce, Auth,
+    BcmFlags, BcmRxSetup, BcmTxSetup, BroadcastMode, Command, ControlMode,
+    Delete, DeleteRange, Echo, FdFlags, FdSend, Filter, GetBitrate, GetBusses,
+    GetState, GetVersion, HeartbeatConfig, IsoTpAddrMode, IsoTpConf, IsoTpMode,
+    LastError, ListJobs, Nop, Open, RawMode, RequestFrames, Send, SetBaud,
+    Sleep, Statistics, SubscribeTx, Update, Wakeup, XlSend, MAX_DATA_LEN,
+    MAX_FD_DATA_LEN, MAX_STATS_INTERVAL, MAX_XL_DATA_LEN, MIN_STATS_INTERVAL,
+    STANDARD_BAUD_RATES,
+};
+use crate::bus::BusType;
+use crate::Mode;
+use core::net::IpAddr;
+use core::str::FromStr;
+use core::time::Duration;
+use embedded_can::{ExtendedId, Id, StandardId};
+use heapless::{String, Vec};
+use winnow::{
+    ascii::{digit1, hex_digit1, multispace0},
+    combinator::{alt, cut_err, delimited, opt, peek, preceded, terminated},
+    error::{ContextError, StrContext},
+    token::{literal, take_till, take_while},
+    ModalResult, Parser,
+};
+
+/// Parse a command keyword followed by its arguments.
+///
+/// Once the keyword has matched the parser does not backtrack, so malformed
+/// arguments fail with `description` rather than trying the remaining
+/// alternatives.
+fn keyword<'a, O>(
+    keyword: &'static str,
+    description: &'static str,
+    arguments: impl Parser<&'a str, O, ContextError>,
+) -> impl Parser<&'a str, O, ContextError> {
+    preceded(
+        literal(keyword),
+        cut_err(arguments.context(StrContext::Label(description))),
+    )
+}
+
+/// Parse a decimal field followed by a space.
+#[inline]
+fn decimal<T: FromStr>(input: &mut &str) -> ModalResult<T> {
+    terminated(digit1.parse_to(), ' ').parse_next(input)
+}
+
+/// Parse space separated hex data bytes.
+#[inline]
+fn hex_data<const N: usize>(input: &mut &str) -> ModalResult<Vec<u8, N>> {
+    take_while(0.., |c: char| c.is_ascii_hexdigit() || c == ' ')
+        .try_map(parse_hex_bytes)
+        .parse_next(input)
+}
+
+/// Parse a token up to the next space or closing bracket.
+#[inline]
+fn token<const N: usize>(input: &mut &str) -> ModalResult<String<N>> {
+    take_till(1.., [' ', '>'])
+        .try_map(String::try_from)
+        .parse_next(input)
+}
+
+/// Parse an interval given as seconds and microseconds.
+#[inline]
+fn interval(input: &mut &str) -> ModalResult<Duration> {
+    (decimal::<u64>, decimal::<u64>)
+        .verify_map(|(secs, micros)| {
+            Duration::from_secs(secs).checked_add(Duration::from_micros(micros))
+        })
+        .parse_next(input)
+}
+
+/// Parse CAN id.
+#[inline]
+fn id(input: &mut &str) -> ModalResult<Id> {
+    (
+        peek(hex_digit1.verify_map(|id: &str| match id.len() {
+            8 => Some(true),
+            3 => Some(false),
+            _ => None,
+        })),
+        terminated(
+            hex_digit1.verify_map(|id| u32::from_str_radix(id, 16).ok()),
+            ' ',
+        ),
+    )
+        .verify_map(|(extended, id)| {
+            if extended {
+                ExtendedId::new(id).map(Id::Extended)
+            } else {
+                StandardId::new(id as u16).map(Id::Standard)
+            }
+        })
+        .parse_next(input)
+}
+
+#[inline]
+fn open(input: &mut &str) -> ModalResult<Open> {
+    keyword(
+        "< open ",
+        "malformed open command",
+        terminated(
+            (
+                alt((
+                    literal("can").value(BusType::Physical),
+                    literal("vcan").value(BusType::Virtual),
+                )),
+                digit1.parse_to().context(StrContext::Label(
+                    "interface index must be decimal",
+                )),
+                opt(preceded(
+                    ' ',
+                    alt((
+                        literal("broadcast").value(Mode::Broadcast),
+                        literal("raw").value(Mode::Raw),
+                        literal("control").value(Mode::Control),
+                        literal("isotp").value(Mode::IsoTp),
+                    )),
+                )),
+            ),
+            " >",
+        ),
+    )
+    .map(|(bus_type, index, mode)| Open {
+        index,
+        bus_type,
+        mode,
+    })
+    .parse_next(input)
+}
+
+#[inline]
+fn add(input: &mut &str) -> ModalResult<Add> {
+    keyword(
+        "< add ",
+        "malformed add command",
+        terminated(
+            (interval, id, decimal::<u8>, hex_data::<MAX_DATA_LEN>).verify(
+                // remote frames carry a data length code but no data
+                |(.., dlc, data)| {
+                    data.is_empty() || *dlc as usize == data.len()
+                },
+            ),
+            '>',
+        ),
+    )
+    .map(|(interval, id, dlc, data)| Add {
+        interval,
+        id,
+        dlc,
+        data,
+    })
+    .parse_next(input)
+}
+
+#[inline]
+fn update(input: &mut &str) -> ModalResult<Update> {
+    keyword(
+        "< update ",
+        "malformed update command",
+        terminated(
+            (id, decimal::<u8>, hex_data::<MAX_DATA_LEN>)
+                .verify(|(_, dlc, data)| *dlc as usize == data.len()),
+            '>',
+        ),
+    )
+    .map(|(id, dlc, data)| Update { id, dlc, data })
+    .parse_next(input)
+}
+
+#[inline]
+fn delete(input: &mut &str) -> ModalResult<Delete> {
+    keyword("< delete ", "malformed delete command", terminated(id, '>'))
+        .map(|id| Delete { id })
+        .parse_next(input)
+}
+
+#[inline]
+fn send(input: &mut &str) -> ModalResult<Send> {
+    keyword(
+        "< send ",
+        "malformed send command",
+        terminated(
+            (id, decimal::<u8>, hex_data::<MAX_DATA_LEN>).verify(
+                // remote frames carry a data length code but no data
+                |(_, dlc, data)| data.is_empty() || *dlc as usize == data.len(),
+            ),
+            '>',
+        ),
+    )
+    .map(|(id, dlc, data)| Send { id, dlc, data })
+    .parse_next(input)
+}
+
+/// Parse filter data bytes, optionally followed by mask bytes.
+///
+/// Unlike [`hex_data`] every byte must be valid.
+#[inline]
+fn filter_bytes(
+    input: &mut &str,
+) -> ModalResult<Vec<u8, { 2 * MAX_DATA_LEN }>> {
+    take_while(0.., |c: char| c.is_ascii_hexdigit() || c == ' ')
+        .verify_map(|bytes: &str| {
+            let mut out = Vec::new();
+
+            for byte in bytes.split_whitespace() {
+                out.push(u8::from_str_radix(byte, 16).ok()?).ok()?;
+            }
+
+            Some(out)
+        })
+        .parse_next(input)
+}
+
+#[inline]
+fn filter(input: &mut &str) -> ModalResult<Filter> {
+    let invert = alt((
+        literal("< filter ").value(false),
+        literal("< filter_inv ").value(true),
+    ))
+    .parse_next(input)?;
+
+    let (interval, id, dlc, bytes) = cut_err(
+        terminated(
+            (interval, id, decimal::<u8>, filter_bytes).verify(
+                // data bytes for the data length code, optionally followed
+                // by the same number of mask bytes
+                |(_, _, dlc, bytes)| {
+                    let dlc = *dlc as usize;
+                    dlc <= MAX_DATA_LEN
+                        && (bytes.len() == dlc || bytes.len() == 2 * dlc)
+                },
+            ),
+            '>',
+        )
+        .context(StrContext::Label("malformed filter command")),
+    )
+    .parse_next(input)?;
+
+    let (data, mask) = bytes.split_at(dlc as usize);
+
+    Ok(Filter {
+        interval,
+        id,
+        dlc,
+        // lengths are checked by the parser
+        data: Vec::from_slice(data).unwrap_or_default(),
+        mask: Vec::from_slice(mask).unwrap_or_default(),
+        invert,
+    })
+}
+
+#[inline]
+fn echo(input: &mut &str) -> ModalResult<Echo> {
+    alt((
+        literal("< echo >").map(|_| Echo::default()),
+        keyword(
+            "< echo ",
+            "malformed echo command",
+            terminated((token, opt(preceded(' ', digit1.parse_to()))), " >"),
+        )
+        .map(|(nonce, elapsed)| Echo {
+            nonce: Some(nonce),
+            elapsed: elapsed.map(Duration::from_micros),
+        }),
+    ))
+    .parse_next(input)
+}
+
+#[inline]
+fn raw_mode(input: &mut &str) -> ModalResult<RawMode> {
+    alt((
+        literal("< rawmode >").value(false),
+        literal("< rawmode loopback >").value(true),
+    ))
+    .map(|loopback| RawMode { loopback })
+    .parse_next(input)
+}
+
+#[inline]
+fn broadcast_mode(input: &mut &str) -> ModalResult<BroadcastMode> {
+    alt((
+        literal("< bcmode >").value(None),
+        keyword(
+            "< bcmode ",
+            "malformed bcmode command",
+            terminated(digit1.parse_to(), " >"),
+        )
+        .map(Some),
+    ))
+    .map(|count| BroadcastMode { count })
+    .parse_next(input)
+}
+
+#[inline]
+fn control_mode(input: &mut &str) -> ModalResult<ControlMode> {
+    alt((
+        literal("< controlmode >").value(None),
+        keyword(
+            "< controlmode ",
+            "malformed controlmode command",
+            terminated(token, " >"),
+        )
+        .map(Some),
+    ))
+    .map(|bus| ControlMode { bus })
+    .parse_next(input)
+}
+
+#[inline]
+fn statistics(input: &mut &str) -> ModalResult<Statistics> {
+    keyword(
+        "< statistics ",
+        "malformed statistics command",
+        terminated(
+            digit1.parse_to().map(Duration::from_millis).verify(
+                |interval: &Duration| {
+                    interval.is_zero()
+                        || (MIN_STATS_INTERVAL..=MAX_STATS_INTERVAL)
+                            .contains(interval)
+                },
+            ),
+            " >",
+        ),
+    )
+    .map(|interval| Statistics { interval })
+    .parse_next(input)
+}
+
+#[inline]
+fn auth(input: &mut &str) -> ModalResult<Auth> {
+    keyword("< auth ", "malformed auth command", terminated(token, " >"))
+        .map(|token| Auth { token })
+        .parse_next(input)
+}
+
+#[inline]
+fn bcm_flags(input: &mut &str) -> ModalResult<BcmFlags> {
+    terminated(
+        hex_digit1.verify_map(|flags| u32::from_str_radix(flags, 16).ok()),
+        ' ',
+    )
+    .map(BcmFlags)
+    .parse_next(input)
+}
+
+#[inline]
+fn bcm_tx_setup(input: &mut &str) -> ModalResult<BcmTxSetup> {
+    keyword(
+        "< tx_setup ",
+        "malformed tx_setup command",
+        terminated(
+            (
+                bcm_flags,
+                interval,
+                id,
+                decimal::<u8>,
+                hex_data::<MAX_DATA_LEN>,
+            )
+                .verify(
+                    // remote frames carry a data length code but no data
+                    |(.., dlc, data)| {
+                        data.is_empty() || *dlc as usize == data.len()
+                    },
+                ),
+            '>',
+        ),
+    )
+    .map(|(flags, interval, id, dlc, data)| BcmTxSetup {
+        flags,
+        interval,
+        id,
+        dlc,
+        data,
+    })
+    .parse_next(input)
+}
+
+#[inline]
+fn bcm_rx_setup(input: &mut &str) -> ModalResult<BcmRxSetup> {
+    keyword(
+        "< rx_setup ",
+        "malformed rx_setup command",
+        terminated(
+            (
+                bcm_flags,
+                interval,
+                id,
+                decimal::<u8>,
+                hex_data::<MAX_DATA_LEN>,
+            )
+                .verify(
+                    // remote frames carry a data length code but no data
+                    |(.., dlc, data)| {
+                        data.is_empty() || *dlc as usize == data.len()
+                    },
+                ),
+            '>',
+        ),
+    )
+    .map(|(flags, interval, id, dlc, mask)| BcmRxSetup {
+        flags,
+        interval,
+        id,
+        dlc,
+        mask,
+    })
+    .parse_next(input)
+}
+
+#[inline]
+fn iso_tp_conf(input: &mut &str) -> ModalResult<IsoTpConf> {
+    keyword(
+        "< isotpconf ",
+        "malformed isotpconf command",
+        terminated(
+            (
+                id,
+                id,
+                terminated(
+                    alt((
+                        literal("normal").value(IsoTpAddrMode::Normal),
+                        literal("extended").value(IsoTpAddrMode::Extended),
+                        literal("mixed").value(IsoTpAddrMode::Mixed),
+                    )),
+                    ' ',
+                ),
+                decimal,
+                decimal,
+                opt(terminated(
+                    hex_digit1.verify_map(|b| u8::from_str_radix(b, 16).ok()),
+                    ' ',
+                )),
+            )
+                // a channel cannot talk to itself
+                .verify(|(src_id, dst_id, ..)| src_id != dst_id),
+            '>',
+        ),
+    )
+    .map(
+        |(src_id, dst_id, addr_mode, block_size, stmin, padding)| IsoTpConf {
+            src_id,
+            dst_id,
+            addr_mode,
+            block_size,
+            stmin,
+            padding,
+        },
+    )
+    .parse_next(input)
+}
+
+#[inline]
+fn subscribe_tx(input: &mut &str) -> ModalResult<SubscribeTx> {
+    keyword(
+        "< subscribe_tx ",
+        "malformed subscribe_tx command",
+        terminated(id, '>'),
+    )
+    .map(|id| SubscribeTx { id })
+    .parse_next(input)
+}
+
+#[inline]
+fn delete_range(input: &mut &str) -> ModalResult<DeleteRange> {
+    keyword(
+        "< delete_range ",
+        "malformed delete_range command",
+        terminated(
+            (id, id).verify(|(start, end)| {
+                let (start_extended, start) = raw_id(*start);
+                let (end_extended, end) = raw_id(*end);
+                start_extended == end_extended && start <= end
+            }),
+            '>',
+        ),
+    )
+    .map(|(start, end)| DeleteRange { start, end })
+    .parse_next(input)
+}
+
+#[inline]
+fn add_count(input: &mut &str) -> ModalResult<AddCount> {
+    keyword(
+        "< add_count ",
+        "malformed add_count command",
+        terminated(
+            (
+                decimal::<u32>.verify(|count| *count > 0),
+                interval,
+                id,
+                decimal::<u8>,
+                hex_data::<MAX_DATA_LEN>,
+            )
+                .verify(
+                    // remote frames carry a data length code but no data
+                    |(.., dlc, data)| {
+                        data.is_empty() || *dlc as usize == data.len()
+                    },
+                ),
+            '>',
+        ),
+    )
+    .map(|(count, interval, id, dlc, data)| AddCount {
+        count,
+        interval,
+        id,
+        dlc,
+        data,
+    })
+    .parse_next(input)
+}
+
+#[inline]
+fn set_baud(input: &mut &str) -> ModalResult<SetBaud> {
+    keyword(
+        "< setbaud ",
+        "malformed setbaud command",
+        terminated(digit1.parse_to(), " >"),
+    )
+    .verify(|rate| STANDARD_BAUD_RATES.contains(rate))
+    .context(StrContext::Label("non-standard baud rate"))
+    .map(|rate| SetBaud { rate })
+    .parse_next(input)
+}
+
+#[inline]
+fn xl_send(input: &mut &str) -> ModalResult<XlSend> {
+    keyword(
+        "< xlsend ",
+        "malformed xlsend command",
+        terminated(
+            (
+                terminated(
+                    hex_digit1.verify_map(|v| u32::from_str_radix(v, 16).ok()),
+                    ' ',
+                ),
+                decimal,
+                decimal,
+                decimal::<u16>,
+                hex_data::<MAX_XL_DATA_LEN>,
+            )
+                .verify(|(_, _, _, dlc, data)| {
+                    *dlc != 0 && *dlc as usize == data.len()
+                }),
+            '>',
+        ),
+    )
+    .map(|(id, priority, vc, dlc, data)| XlSend {
+        id,
+        priority,
+        vc,
+        dlc,
+        data: xl_data(data),
+    })
+    .parse_next(input)
+}
+
+#[inline]
+fn fd_send(input: &mut &str) -> ModalResult<FdSend> {
+    keyword(
+        "< fdsend ",
+        "malformed fdsend command",
+        terminated(
+            (
+                id,
+                terminated(
+                    hex_digit1
+                        .verify_map(|v| u8::from_str_radix(v, 16).ok())
+                        .map(FdFlags::from_bits),
+                    ' ',
+                ),
+                decimal::<u8>,
+                hex_data::<MAX_FD_DATA_LEN>,
+            )
+                .verify(|(_, _, len, data)| {
+                    matches!(len, 0..=8 | 12 | 16 | 20 | 24 | 32 | 48 | 64)
+                        && *len as usize == data.len()
+                }),
+            '>',
+        ),
+    )
+    .map(|(id, fd_flags, len, data)| FdSend {
+        id,
+        fd_flags,
+        len,
+        data,
+    })
+    .parse_next(input)
+}
+
+#[inline]
+fn heartbeat_config(input: &mut &str) -> ModalResult<HeartbeatConfig> {
+    keyword(
+        "< heartbeat ",
+        "malformed heartbeat command",
+        terminated(digit1.parse_to(), " >"),
+    )
+    .map(|millis| HeartbeatConfig {
+        interval: Duration::from_millis(millis),
+    })
+    .parse_next(input)
+}
+
+#[inline]
+fn announce(input: &mut &str) -> ModalResult<Announce> {
+    keyword(
+        "< announce ",
+        "malformed announce command",
+        terminated(
+            (
+                terminated(token, ' '),
+                terminated(take_till(1.., ' ').try_map(IpAddr::from_str), ' '),
+                decimal,
+            ),
+            " >",
+        ),
+    )
+    .map(|(name, ip, port)| Announce { name, ip, port })
+    .parse_next(input)
+}
+
+#[inline]
+fn request_frames(input: &mut &str) -> ModalResult<RequestFrames> {
+    keyword(
+        "< request_frames ",
+        "malformed request_frames command",
+        terminated(id, '>'),
+    )
+    .map(|id| RequestFrames { id })
+    .parse_next(input)
+}
+
+/// Parse a socketcand command.
+///
+/// Equivalent to [`wire::command`](super::command), advancing `input` past
+/// the command and any surrounding whitespace.
+#[inline]
+pub fn command(input: &mut &str) -> ModalResult<Command> {
+    // some clients separate commands with whitespace or line endings
+    delimited(
+        multispace0,
+        alt((
+            alt((
+                open.map(Command::Open),
+                add.map(Command::Add),
+                update.map(Command::Update),
+                delete.map(Command::Delete),
+                send.map(Command::Send),
+                filter.map(Command::Filter),
+                echo.map(Command::Echo),
+                raw_mode.map(Command::RawMode),
+                broadcast_mode.map(Command::BroadcastMode),
+                control_mode.map(Command::ControlMode),
+                literal("< isotpmode >").value(Command::IsoTpMode(IsoTpMode)),
+                statistics.map(Command::Statistics),
+                auth.map(Command::Auth),
+                literal("< list_jobs >").value(Command::ListJobs(ListJobs)),
+            )),
+            alt((
+                literal("< get_busses >").value(Command::GetBusses(GetBusses)),
+                bcm_tx_setup.map(Command::BcmTxSetup),
+                bcm_rx_setup.map(Command::BcmRxSetup),
+                iso_tp_conf.map(Command::IsoTpConf),
+                literal("< nop >").value(Command::Nop(Nop)),
+                delete_range.map(Command::DeleteRange),
+                add_count.map(Command::AddCount),
+                set_baud.map(Command::SetBaud),
+                xl_send.map(Command::XlSend),
+                literal("< get_state >").value(Command::GetState(GetState)),
+                literal("< sleep >").value(Command::Sleep(Sleep)),
+                literal("< wakeup >").value(Command::Wakeup(Wakeup)),
+                literal("< get_version >")
+                    .value(Command::GetVersion(GetVersion)),
+                fd_send.map(Command::FdSend),
+                subscribe_tx.map(Command::SubscribeTx),
+                heartbeat_config.map(Command::HeartbeatConfig),
+                literal("< getbitrate >")
+                    .value(Command::GetBitrate(GetBitrate)),
+                literal("< lasterror >").value(Command::LastError(LastError)),
+                announce.map(Command::Announce),
+                request_frames.map(Command::RequestFrames),
+            )),
+        )),
+        multispace0,
+    )
+    .parse_next(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Remaining input and command, if the input parsed.
+    type Parsed<'a> = Option<(&'a str, Command)>;
+
+    /// Parse `input` with both parsers.
+    fn both(input: &str) -> (Parsed<'_>, Parsed<'_>) {
+        let nom = super::super::command(input).ok();
+        let winnow = command.parse_peek(input).ok();
+        (nom, winnow)
+    }
+
+    #[test]
+    fn matches_nom_on_examples() {
+        let inputs = [
+            "< open can0 >",
+            "< open vcan1 raw >",
+            "  < add 1 500000 123 2 01 02 >\r\n",
+            "< update 12345678 1 FF >",
+            "< delete 123 >",
+            "< send 123 2 >",
+            "< send 123 2 01 >",
+            "< filter_inv 0 100000 123 2 FF 00 0F F0 >",
+            "< filter 0 0 123 1 GG >",
+            "< echo abc123 250 >",
+            "< bcmode 10 >",
+            "< controlmode can0 >",
+            "< statistics 5 >",
+            "< isotpconf 7E0 7E0 normal 8 10 >",
+            "< delete_range 1FF 100 >",
+            "< add_count 0 0 100000 123 1 AA >",
+            "< setbaud 500000 >",
+            "< setbaud 123 >",
+            "< xlsend 00000123 7 1 3 01 02 03 >",
+            "< fdsend 123 3 9 00 01 02 03 04 05 06 07 08 >",
+            "< add 18446744073709551615 1000000 123 0 >",
+            "< get_version >< nop >",
+            "< update 123 3 11 22 >",
+            "< add 0 0 123 2 01 >",
+            "< add_count 1 0 0 123 2 01 >",
+            "< tx_setup 3 0 100000 123 1 01 02 >",
+            "< rx_setup 20 0 0 123 2 FF >",
+            "< bogus >",
+        ];
+
+        for input in inputs {
+            let (nom, winnow) = both(input);
+            assert_eq!(nom, winnow, "{:?}", input);
+        }
+    }
+
+    /// Commands assembled from valid and invalid fragments, so that most
+    /// inputs get past the keyword.
+    fn fragments() -> impl Strategy<Value = std::string::String> {
+        let keyword = prop::sample::select(
+            &[
+                "open",
+                "add",
+                "update",
+                "delete",
+                "send",
+                "filter",
+                "filter_inv",
+                "echo",
+                "rawmode",
+                "bcmode",
+                "controlmode",
+                "isotpmode",
+                "statistics",
+                "auth",
+                "list_jobs",
+                "get_busses",
+                "tx_setup",
+                "rx_setup",
+                "isotpconf",
+                "nop",
+                "delete_range",
+                "add_count",
+                "setbaud",
+                "xlsend",
+                "get_state",
+                "sleep",
+                "wakeup",
+                "get_version",
+                "fdsend",
+                "subscribe_tx",
+                "heartbeat",
+                "getbitrate",
+                "lasterror",
+                "announce",
+                "request_frames",
+            ][..],
+        );
+        let field = prop::string::string_regex(
+            "(can[0-9]|vcan[0-9]{1,6}|raw|loopback|normal|mixed|[0-9]{1,6}|[0-9A-Fa-f]{1,3}|[0-9A-F]{8}|[a-z]{1,4})",
+        )
+        .unwrap();
+
+        (
+            "[ \t\r\n]{0,2}",
+            keyword,
+            prop::collection::vec(field, 0..12),
+            prop::sample::select(&[" >", ">", "", " > "][..]),
+        )
+            .prop_map(|(lead, keyword, fields, end)| {
+                let mut out = std::format!("{}< {}", lead, keyword);
+                for field in fields {
+                    out.push(' ');
+                    out.push_str(&field);
+                }
+                out.push_str(end);
+                out
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn matches_nom_on_fragments(input in fragments()) {
+            let (nom, winnow) = both(&input);
+            prop_assert_eq!(nom, winnow);
+        }
+
+        #[test]
+        fn matches_nom_on_any_string(input in "\\PC{0,64}") {
+            let (nom, winnow) = both(&input);
+            prop_assert_eq!(nom, winnow);
+        }
+    }
+}